@@ -6,6 +6,7 @@ fn main() {
     example_runcode();
     example_runfile();
     example_variables();
+    example_interpreter();
 }
 
 fn example_runcode() {
@@ -54,3 +55,46 @@ fn example_variables() {
 
     ring_state_delete(state);
 }
+
+fn example_interpreter() {
+    println!("\n4. Owned interpreter (Ring)\n");
+
+    let ring = Ring::new();
+    ring.run_code(r#"greeting = "Hello from an owned interpreter""#);
+    ring.run_code(r#"? "   " + greeting"#);
+
+    if let Some(var) = ring.find_var("greeting") {
+        let value = ring_list_getstring_str(var, RING_VAR_VALUE);
+        println!("   greeting (read in Rust) = {}", value);
+    }
+
+    // Typed access to globals, no Ring source formatting needed
+    ring.set_global("scores", vec![90.5, 72.0, 88.25]);
+    ring.run_code("best = max(scores)");
+    match ring.get_global::<f64>("best") {
+        Ok(best) => println!("   best (read in Rust) = {}", best),
+        Err(e) => println!("   error: {}", e),
+    }
+
+    // Calling a Ring function with arguments
+    ring.run_code("func greet name, times return copy('Hi ' + name + '! ', times)");
+    match ring.call::<String, _>("greet", ("Rust", 2)) {
+        Ok(text) => println!("   greet returned: {}", text),
+        Err(e) => println!("   error: {}", e),
+    }
+    if let Err(e) = ring.call::<(), _>("no_such_function", ()) {
+        println!("   expected error: {}", e);
+    }
+
+    // Errors raised inside a function report that function and line
+    let code = "func average values\n  return sum / len(values)\naverage([])";
+    if let Err(e) = ring.eval(code) {
+        println!("   expected error: {}", e);
+        println!(
+            "   raised in {:?} at line {:?}",
+            e.function.as_deref(),
+            e.line
+        );
+    }
+    // The state is deleted when `ring` goes out of scope
+}
@@ -74,6 +74,9 @@
 //! | `pub value: i64` field | `mylib_counter_get_value(ptr)`, `mylib_counter_set_value(ptr, v)` |
 //! | `impl Counter { pub fn new() }` | Replaces default `_new` with custom constructor |
 //! | `pub fn increment(&mut self)` | `mylib_counter_increment(ptr)` |
+//! | `pub enum Mode { Fast, Slow }` | Globals `mylib_mode_fast`, `mylib_mode_slow` (and functions of the same name), `mylib_mode_name(v)` |
+//! | `pub trait Shape { fn area(&self) }` | `mylib_shape_area(ptr)` for any implementor |
+//! | `#[ring(export)] pub const VERSION: &str` | `mylib_get_version()`, and the global `mylib_version` set by `loadlib()` |
 //!
 //! ## Ring Usage
 //!
@@ -89,37 +92,110 @@
 //! mylib_counter_delete(obj)
 //! ```
 //!
+//! Structs returned to Ring (from `_new`, constructors, methods or getters) are
+//! managed C pointers: Ring's garbage collector drops the Rust value once nothing
+//! refers to it. Calling `_delete` drops it right away and sets the pointer to
+//! `NULL`, so using the object afterwards, or deleting it twice, raises a Ring
+//! error instead of touching freed memory.
+//!
+//! ## Attributes
+//!
+//! | Attribute | On | Effect |
+//! |-----------|----|--------|
+//! | `#[ring(name = "...")]` | fn, method, struct, field, enum, variant, trait, const, static | Replaces the item's part of the Ring name |
+//! | `#[ring(skip)]` | fn, method, struct, field, impl, enum, trait | Not exported to Ring |
+//! | `#[ring(export)]` | pub const, pub static | Exported to Ring as a global and a getter; constants are Rust-only without it |
+//! | `#[ring(readonly)]`, `#[ring(getter_only)]` | field | No setter |
+//! | `#[ring(prefix = "...")]` | impl | Replaces `prefix_struct_` for the block's methods |
+//! | `#[ring(default = expr)]` | parameter | Used when the trailing argument is omitted |
+//! | `#[ring(instantiate(Stack<f64> = "numstack", ...))]` | generic struct, fn | One export per listed instantiation, named by the string |
+//! | `#[ring(list)]` | parameter, field, fn, method | Byte buffer (or the return value's) as a list of numbers, not a string |
+//! | `#[ring(view)]` | struct-typed field | Getter returns a pointer into the parent, which it keeps alive |
+//! | `#[ring(serde)]` | parameter, field, fn, method | Value (or the return value) converted with serde; needs the `serde` feature of `ring-lang-rs` |
+//!
+//! ## Traits
+//!
+//! A trait defined in the extension gets `prefix_trait_method(ptr, ...)`
+//! functions that accept a pointer to any exported struct implementing it and
+//! call the method through `&dyn Trait` (see `ring_pointer_dyn!`). `&dyn Trait`
+//! and `&mut dyn Trait` parameters work the same way. Methods of
+//! `impl Trait for Struct` blocks are exported for the struct without `pub`.
+//!
+//! ## Ring Classes
+//!
+//! A Ring class per struct (pointer in `pObject`, one `Func` per method and
+//! field accessor) is emitted as the `RING_CLASSES` constant. Methods and
+//! getters returning structs, or lists of them, return class instances. The
+//! macro doesn't write files; save the source from a test, for example:
+//!
+//! ```rust,ignore
+//! #[test]
+//! fn write_ring_classes() {
+//!     std::fs::write("mylib.ring", RING_CLASSES).unwrap();
+//! }
+//! ```
+//!
+//! ```ring
+//! load "mylib.ring"
+//! c = new Counter("test", 0)
+//! c.increment()
+//! ? c.value()
+//! c.delete()
+//! ```
+//!
 //! ## Supported Types
 //!
+//! Parameters, return values and fields convert through the `FromRing` and
+//! `IntoRing` traits of `ring-lang-rs`, so type aliases, full paths such as
+//! `std::string::String` and your own types implementing them all work. A type
+//! without an impl is a compile error pointing at that type.
+//!
 //! ### Return Types
 //!
 //! | Rust Type | Ring Representation |
 //! |-----------|---------------------|
-//! | `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32`, `f64` | Number |
+//! | Integers (`i8`..`i128`, `u8`..`u128`, `isize`, `usize`), `f32`, `f64` | Number |
 //! | `bool` | Number (1 or 0) |
-//! | `String`, `&str` | String |
+//! | `String`, `&str`, `Cow<str>`, `char` | String |
+//! | `Vec<u8>`, `&[u8]`, `Box<[u8]>`, `Bytes` | String (binary-safe), or list with `#[ring(list)]` |
 //! | `Vec<T>` | List |
 //! | `Vec<Vec<T>>` | Nested list (2D array) |
-//! | `Option<T>` | Value or empty string for None |
+//! | `Option<T>` | Value or empty string for None (null C pointer for structs) |
 //! | `Result<T, E>` | Value on Ok, Ring error on Err |
 //! | `(A, B)`, `(A, B, C)` | List (tuple as list) |
 //! | `Box<T>` | Unwrapped inner value |
-//! | `HashMap<K, V>` | List of `[key, value]` pairs |
-//! | Custom structs | C pointer |
+//! | `HashMap<K, V>`, `BTreeMap<K, V>`, `IndexMap<K, V>` | List of `[key, value]` pairs (hashed for string keys) |
+//! | `RingValue` | Any value |
+//! | C-like enums | Number |
+//! | Enums with data | List `[variant, fields...]` |
+//! | Custom structs | C pointer (boxed) |
+//! | `Vec<Struct>` | List of C pointers |
+//!
+//! Everything except custom structs goes through the `FromRing`/`IntoRing`
+//! traits of `ring-lang-rs`, the same ones `ring_get!`/`ring_ret!` use.
 //!
 //! ### Parameter Types
 //!
 //! | Rust Type | Ring Input |
 //! |-----------|------------|
-//! | `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32`, `f64` | Number |
+//! | Integers, `f32`, `f64` | Number (out of range is an error) |
 //! | `bool` | Number (non-zero = true) |
-//! | `&str`, `String` | String |
+//! | `&str`, `String`, `Cow<str>`, `char` | String |
+//! | `Vec<u8>`, `&[u8]`, `Box<[u8]>`, `Bytes` | String (binary-safe), or list with `#[ring(list)]` |
 //! | `&T` (struct reference) | C pointer |
 //! | `&mut T` (mutable struct reference) | C pointer |
+//! | `&dyn Trait`, `&mut dyn Trait` (exported trait) | C pointer to any implementor |
 //! | `Vec<T>` | List |
 //! | `&[T]` (slice) | List |
-//! | `Option<T>` | Value or empty string for None |
-//! | Custom structs | C pointer |
+//! | `Option<T>` | Value, or empty string or null C pointer for None; may be omitted when trailing |
+//! | `(A, B)`, `(A, B, C)` | List with one item per element |
+//! | `Box<T>` | Inner value |
+//! | `HashMap<K, V>`, `BTreeMap<K, V>`, `IndexMap<K, V>` | List of `[key, value]` pairs, e.g. `[:a = 1]` |
+//! | `RingValue` | Any value |
+//! | C-like enums | Number or variant name |
+//! | Enums with data | List `[variant, fields...]` or variant name |
+//! | Custom structs | C pointer (a clone, needs `Clone`) |
+//! | `impl Fn(A) -> R`, `&dyn Fn`, `&mut dyn FnMut`, `Box<dyn Fn>` | Ring function (name or anonymous `func`) |
 //!
 //! ### Field Types (Getters/Setters)
 //!
@@ -129,18 +205,758 @@
 //! | `String` | String | String |
 //! | `Vec<T>` | List | List |
 //! | `Option<T>` | Value or empty string | Value or empty string |
-//! | Struct | C pointer | C pointer |
+//! | Struct | C pointer to a copy (into the parent with `#[ring(view)]`) | C pointer (copied) |
 
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::{format_ident, quote};
-use std::collections::HashSet;
+use quote::{format_ident, quote, quote_spanned};
+use std::collections::{HashMap, HashSet};
 use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
 use syn::{
-    FnArg, Ident, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl, ItemStruct, Pat, ReturnType, Token,
-    Type, Visibility, parse_macro_input,
+    Attribute, FnArg, Ident, ImplItem, ImplItemFn, Item, ItemEnum, ItemFn, ItemImpl, ItemStruct,
+    ItemTrait, Pat, ReturnType, Token, TraitItem, Type, Visibility, parse_macro_input,
 };
 
+/// Options from `#[ring(...)]` attributes.
+#[derive(Default)]
+struct RingAttrs {
+    /// Replaces the item's own part of the Ring name.
+    name: Option<String>,
+    /// Replaces `prefix_struct_` for the methods of an impl block.
+    prefix: Option<String>,
+    skip: bool,
+    /// `readonly` / `getter_only`: no setter for a field.
+    readonly: bool,
+    /// Value of a parameter the Ring caller left out.
+    default: Option<syn::Expr>,
+    /// Pass a byte buffer as a list of numbers instead of a string.
+    list: bool,
+    /// Pass the value through serde (`RingSerde`).
+    serde: bool,
+    /// Return a struct field as a pointer into its parent, not a copy.
+    view: bool,
+    /// Export a `pub const` or `pub static` as a Ring global.
+    export: bool,
+    /// Concrete types of a generic struct or fn to export, with their names.
+    instantiate: Vec<Instantiation>,
+}
+
+impl RingAttrs {
+    fn passing(&self) -> Passing {
+        if self.serde {
+            Passing::Serde
+        } else if self.list {
+            Passing::List
+        } else {
+            Passing::Default
+        }
+    }
+}
+
+/// How a parameter, return value or field crosses to Ring.
+#[derive(Clone, Copy, PartialEq)]
+enum Passing {
+    Default,
+    /// `#[ring(list)]`: byte buffers as lists of numbers.
+    List,
+    /// `#[ring(serde)]`: through `RingSerde`.
+    Serde,
+}
+
+/// `Stack<f64> = "numstack"` in `#[ring(instantiate(...))]`.
+struct Instantiation {
+    ty: Type,
+    name: syn::LitStr,
+}
+
+impl Parse for Instantiation {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ty = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let name: syn::LitStr = input.parse()?;
+        check_ring_name(&name)?;
+        Ok(Instantiation { ty, name })
+    }
+}
+
+fn check_ring_name(lit: &syn::LitStr) -> syn::Result<()> {
+    let value = lit.value();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(syn::Error::new(
+            lit.span(),
+            "Ring names may only contain letters, digits and '_'",
+        ));
+    }
+    Ok(())
+}
+
+/// Parse the `#[ring(...)]` attributes in `attrs`, accepting only `allowed` keys.
+fn parse_ring_attrs(attrs: &[Attribute], allowed: &[&str]) -> syn::Result<RingAttrs> {
+    let mut out = RingAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("ring")) {
+        attr.parse_nested_meta(|meta| {
+            let key = meta
+                .path
+                .get_ident()
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            if !allowed.contains(&key.as_str()) {
+                return Err(meta.error(format!(
+                    "unsupported ring attribute here, expected one of: {}",
+                    allowed.join(", ")
+                )));
+            }
+            match key.as_str() {
+                "name" => {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    check_ring_name(&lit)?;
+                    out.name = Some(lit.value());
+                }
+                "prefix" => {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    if !lit.value().is_empty() {
+                        check_ring_name(&lit)?;
+                    }
+                    out.prefix = Some(lit.value());
+                }
+                "skip" => out.skip = true,
+                "readonly" | "getter_only" => out.readonly = true,
+                "default" => out.default = Some(meta.value()?.parse()?),
+                "list" => out.list = true,
+                "serde" => out.serde = true,
+                "view" => out.view = true,
+                "export" => out.export = true,
+                "instantiate" => {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let entries = content.parse_terminated(Instantiation::parse, Token![,])?;
+                    out.instantiate.extend(entries);
+                }
+                _ => unreachable!(),
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+const STRUCT_ATTRS: &[&str] = &["name", "skip", "instantiate"];
+const TYPE_ATTRS: &[&str] = &["name", "skip"];
+const FIELD_ATTRS: &[&str] = &[
+    "name",
+    "skip",
+    "readonly",
+    "getter_only",
+    "list",
+    "serde",
+    "view",
+];
+const IMPL_ATTRS: &[&str] = &["prefix", "skip"];
+const FN_ATTRS: &[&str] = &["name", "skip", "instantiate", "list", "serde"];
+const METHOD_ATTRS: &[&str] = &["name", "skip", "list", "serde"];
+const VARIANT_ATTRS: &[&str] = &["name"];
+const CONST_ATTRS: &[&str] = &["name", "export"];
+const PARAM_ATTRS: &[&str] = &["default", "list", "serde"];
+
+/// Attributes that were already validated by [`validate_ring_attrs`].
+fn ring_attrs(attrs: &[Attribute]) -> RingAttrs {
+    parse_ring_attrs(
+        attrs,
+        &[
+            "name",
+            "prefix",
+            "skip",
+            "readonly",
+            "getter_only",
+            "default",
+            "list",
+            "serde",
+            "view",
+            "export",
+            "instantiate",
+        ],
+    )
+    .unwrap_or_default()
+}
+
+/// Check every `#[ring(...)]` attribute up front, so errors point at the
+/// attribute and the generators can assume valid input.
+fn validate_ring_attrs(items: &[Item]) -> syn::Result<()> {
+    let mut errors: Vec<syn::Error> = Vec::new();
+    let structs: HashSet<String> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) if !ring_attrs(&s.attrs).skip => Some(s.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    for item in items {
+        match item {
+            Item::Struct(s) => {
+                if let Some(attrs) = check_attrs(&mut errors, &s.attrs, STRUCT_ATTRS) {
+                    check_instances(&mut errors, &s.ident, &s.generics, &attrs);
+                }
+                for field in &s.fields {
+                    if let Some(attrs) = check_attrs(&mut errors, &field.attrs, FIELD_ATTRS) {
+                        check_passing(&mut errors, &attrs, Some(&field.ty), field);
+                        let is_struct = matches!(field.ty, Type::Path(_))
+                            && last_segment(&field.ty)
+                                .is_some_and(|s| structs.contains(&s.ident.to_string()));
+                        if attrs.view && !is_struct {
+                            errors.push(syn::Error::new_spanned(
+                                &field.ty,
+                                "#[ring(view)] is for fields of a struct type exported by this extension",
+                            ));
+                        }
+                    }
+                }
+            }
+            Item::Impl(i) => {
+                let attrs = check_attrs(&mut errors, &i.attrs, IMPL_ATTRS);
+                if attrs.is_some_and(|a| a.prefix.is_some()) && !type_args(&i.self_ty).is_empty() {
+                    errors.push(syn::Error::new_spanned(
+                        &i.self_ty,
+                        "#[ring(prefix)] can't be used on impls of generic structs",
+                    ));
+                }
+                for impl_item in &i.items {
+                    if let ImplItem::Fn(f) = impl_item {
+                        if let Some(attrs) = check_attrs(&mut errors, &f.attrs, METHOD_ATTRS) {
+                            check_return_passing(&mut errors, &attrs, &f.sig);
+                        }
+                        check_params(&mut errors, &f.sig);
+                    }
+                }
+            }
+            Item::Fn(f) => {
+                if let Some(attrs) = check_attrs(&mut errors, &f.attrs, FN_ATTRS) {
+                    check_instances(&mut errors, &f.sig.ident, &f.sig.generics, &attrs);
+                    check_return_passing(&mut errors, &attrs, &f.sig);
+                }
+                check_params(&mut errors, &f.sig);
+            }
+            Item::Trait(t) => {
+                check_attrs(&mut errors, &t.attrs, TYPE_ATTRS);
+                for trait_item in &t.items {
+                    if let TraitItem::Fn(f) = trait_item {
+                        if let Some(attrs) = check_attrs(&mut errors, &f.attrs, METHOD_ATTRS) {
+                            check_return_passing(&mut errors, &attrs, &f.sig);
+                        }
+                        check_params(&mut errors, &f.sig);
+                    }
+                }
+            }
+            Item::Enum(e) => {
+                check_attrs(&mut errors, &e.attrs, TYPE_ATTRS);
+                for variant in &e.variants {
+                    check_attrs(&mut errors, &variant.attrs, VARIANT_ATTRS);
+                }
+            }
+            Item::Const(c) => {
+                if let Some(attrs) = check_attrs(&mut errors, &c.attrs, CONST_ATTRS) {
+                    check_export(&mut errors, &attrs, &c.vis, false, &c.ident);
+                }
+            }
+            Item::Static(s) => {
+                if let Some(attrs) = check_attrs(&mut errors, &s.attrs, CONST_ATTRS) {
+                    let mutable = !matches!(s.mutability, syn::StaticMutability::None);
+                    check_export(&mut errors, &attrs, &s.vis, mutable, &s.ident);
+                }
+            }
+            _ => {}
+        }
+    }
+    match errors.into_iter().reduce(|mut all, e| {
+        all.combine(e);
+        all
+    }) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Copy the `#[ring(...)]` attributes of a trait's methods (and their
+/// parameters) to the methods of its impls that don't have their own, so
+/// `name`, `skip` and `default` apply to every implementor.
+fn inherit_trait_attrs(items: &mut [Item]) {
+    let mut trait_methods: HashMap<(String, String), syn::Signature> = HashMap::new();
+    let mut trait_attrs: HashMap<(String, String), Vec<Attribute>> = HashMap::new();
+    for item in items.iter() {
+        let Item::Trait(t) = item else { continue };
+        for trait_item in &t.items {
+            let TraitItem::Fn(f) = trait_item else {
+                continue;
+            };
+            let key = (t.ident.to_string(), f.sig.ident.to_string());
+            trait_attrs.insert(key.clone(), ring_attr_list(&f.attrs));
+            trait_methods.insert(key, f.sig.clone());
+        }
+    }
+    for item in items.iter_mut() {
+        let Item::Impl(i) = item else { continue };
+        let Some(trait_name) = impl_trait_name(i) else {
+            continue;
+        };
+        for impl_item in &mut i.items {
+            let ImplItem::Fn(f) = impl_item else { continue };
+            let key = (trait_name.clone(), f.sig.ident.to_string());
+            if let Some(attrs) = trait_attrs.get(&key) {
+                if ring_attr_list(&f.attrs).is_empty() {
+                    f.attrs.extend(attrs.iter().cloned());
+                }
+            }
+            let Some(sig) = trait_methods.get(&key) else {
+                continue;
+            };
+            for (arg, trait_arg) in f.sig.inputs.iter_mut().zip(&sig.inputs) {
+                if let (FnArg::Typed(pat), FnArg::Typed(trait_pat)) = (arg, trait_arg) {
+                    if ring_attr_list(&pat.attrs).is_empty() {
+                        pat.attrs.extend(ring_attr_list(&trait_pat.attrs));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn ring_attr_list(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("ring"))
+        .cloned()
+        .collect()
+}
+
+fn check_attrs(
+    errors: &mut Vec<syn::Error>,
+    attrs: &[Attribute],
+    allowed: &[&str],
+) -> Option<RingAttrs> {
+    parse_ring_attrs(attrs, allowed)
+        .map_err(|e| errors.push(e))
+        .ok()
+}
+
+/// `#[ring(export)]` is for `pub const` and `pub static` items, and `name`
+/// only means something on an exported one.
+fn check_export(
+    errors: &mut Vec<syn::Error>,
+    attrs: &RingAttrs,
+    vis: &Visibility,
+    mutable: bool,
+    ident: &Ident,
+) {
+    let message = if !attrs.export {
+        attrs
+            .name
+            .as_ref()
+            .map(|_| format!("`{ident}` isn't exported, add #[ring(export)] to use `name`"))
+    } else if mutable {
+        Some(format!("`static mut {ident}` can't be exported to Ring"))
+    } else if !matches!(vis, Visibility::Public(_)) {
+        Some(format!("`{ident}` must be `pub` to be exported to Ring"))
+    } else {
+        None
+    };
+    if let Some(message) = message {
+        errors.push(syn::Error::new_spanned(ident, message));
+    }
+}
+
+/// Generic structs and fns are exported once per `#[ring(instantiate)]`
+/// entry, each naming the item with one type per type parameter.
+fn check_instances(
+    errors: &mut Vec<syn::Error>,
+    ident: &Ident,
+    generics: &syn::Generics,
+    attrs: &RingAttrs,
+) {
+    let params = generics.type_params().count();
+    if attrs.skip {
+        return;
+    }
+    if params == 0 {
+        if let Some(first) = attrs.instantiate.first() {
+            errors.push(syn::Error::new_spanned(
+                &first.ty,
+                format!("`{ident}` isn't generic, `instantiate` is for generic items"),
+            ));
+        }
+        return;
+    }
+    if attrs.instantiate.is_empty() {
+        errors.push(syn::Error::new_spanned(
+            generics,
+            format!(
+                "generic items need #[ring(instantiate({ident}<...> = \"name\", ...))] \
+                 to be exported, or #[ring(skip)]"
+            ),
+        ));
+    }
+    for entry in &attrs.instantiate {
+        let named = last_segment(&entry.ty).is_some_and(|s| s.ident == *ident);
+        if !named || type_args(&entry.ty).len() != params {
+            errors.push(syn::Error::new_spanned(
+                &entry.ty,
+                format!("expected `{ident}` with {params} type argument(s)"),
+            ));
+        }
+    }
+}
+
+/// `#[ring(list)]` only changes how byte buffers are passed, and
+/// `#[ring(serde)]` needs a value to pass.
+fn check_passing(
+    errors: &mut Vec<syn::Error>,
+    attrs: &RingAttrs,
+    ty: Option<&Type>,
+    tokens: impl quote::ToTokens,
+) {
+    let message = if attrs.serde && attrs.list {
+        "#[ring(serde)] and #[ring(list)] can't be combined"
+    } else if attrs.serde && attrs.view {
+        "#[ring(serde)] and #[ring(view)] can't be combined"
+    } else if attrs.serde && ty.is_none() {
+        "#[ring(serde)] on a fn or method applies to its return value"
+    } else if attrs.list && !ty.is_some_and(is_byte_buffer) {
+        "#[ring(list)] only applies to byte buffers (Vec<u8>, &[u8], Box<[u8]>, Bytes)"
+    } else {
+        return;
+    };
+    errors.push(syn::Error::new_spanned(tokens, message));
+}
+
+/// `#[ring(list)]` and `#[ring(serde)]` on a fn or method apply to its
+/// return value.
+fn check_return_passing(errors: &mut Vec<syn::Error>, attrs: &RingAttrs, sig: &syn::Signature) {
+    let ty = match &sig.output {
+        ReturnType::Type(_, ty) => Some(result_ok_type(ty).unwrap_or(ty)),
+        ReturnType::Default => None,
+    };
+    match ty {
+        Some(ty) => check_passing(errors, attrs, Some(ty), ty),
+        None => check_passing(errors, attrs, None, &sig.ident),
+    }
+}
+
+/// Check the parameter attributes of `sig`, and that `#[ring(default)]`
+/// parameters are only followed by optional ones.
+fn check_params(errors: &mut Vec<syn::Error>, sig: &syn::Signature) {
+    let mut seen_default = false;
+    for arg in &sig.inputs {
+        let FnArg::Typed(pat) = arg else { continue };
+        let attrs = match parse_ring_attrs(&pat.attrs, PARAM_ATTRS) {
+            Ok(attrs) => attrs,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let ty = &pat.ty;
+        check_passing(errors, &attrs, Some(ty), ty);
+        if attrs.default.is_some() {
+            seen_default = true;
+            if is_slice_ref(ty) {
+                errors.push(syn::Error::new_spanned(
+                    ty,
+                    "slice parameters can't have a default, use Vec<T>",
+                ));
+            } else if callback_signature(ty).is_some() {
+                errors.push(syn::Error::new_spanned(
+                    ty,
+                    "callback parameters can't have a default",
+                ));
+            }
+        } else if seen_default && !is_option(ty) {
+            errors.push(syn::Error::new_spanned(
+                pat,
+                "parameters after a #[ring(default)] parameter must be optional",
+            ));
+        }
+    }
+}
+
+fn strip_ring_attrs(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|a| !a.path().is_ident("ring"));
+}
+
+fn strip_param_attrs(sig: &mut syn::Signature) {
+    for arg in sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat) = arg {
+            strip_ring_attrs(&mut pat.attrs);
+        }
+    }
+}
+
+/// The item as written, minus the `#[ring(...)]` attributes rustc doesn't know.
+fn strip_item(item: &Item) -> Item {
+    let mut item = item.clone();
+    match &mut item {
+        Item::Struct(s) => {
+            strip_ring_attrs(&mut s.attrs);
+            for field in s.fields.iter_mut() {
+                strip_ring_attrs(&mut field.attrs);
+            }
+        }
+        Item::Impl(i) => {
+            strip_ring_attrs(&mut i.attrs);
+            for impl_item in &mut i.items {
+                if let ImplItem::Fn(f) = impl_item {
+                    strip_ring_attrs(&mut f.attrs);
+                    strip_param_attrs(&mut f.sig);
+                }
+            }
+        }
+        Item::Fn(f) => {
+            strip_ring_attrs(&mut f.attrs);
+            strip_param_attrs(&mut f.sig);
+        }
+        Item::Trait(t) => {
+            strip_ring_attrs(&mut t.attrs);
+            for trait_item in &mut t.items {
+                if let TraitItem::Fn(f) = trait_item {
+                    strip_ring_attrs(&mut f.attrs);
+                    strip_param_attrs(&mut f.sig);
+                }
+            }
+        }
+        Item::Enum(e) => {
+            strip_ring_attrs(&mut e.attrs);
+            for variant in e.variants.iter_mut() {
+                strip_ring_attrs(&mut variant.attrs);
+            }
+        }
+        Item::Const(c) => strip_ring_attrs(&mut c.attrs),
+        Item::Static(s) => strip_ring_attrs(&mut s.attrs),
+        _ => {}
+    }
+    item
+}
+
+/// A struct as exported to Ring: the struct itself, or one
+/// `#[ring(instantiate(...))]` entry of a generic struct.
+#[derive(Clone)]
+struct StructInstance {
+    /// `Counter` or `Stack<f64>`.
+    ty: Type,
+    /// `prefix_counter_`, the start of its functions' Ring names.
+    base: String,
+    /// Ring class name, and the C pointer type name of an instantiation.
+    class_name: String,
+    /// The struct's type parameters and their types in this instance.
+    params: Vec<(Ident, Type)>,
+}
+
+impl StructInstance {
+    fn subst(&self) -> TypeSubst {
+        TypeSubst {
+            self_type: Some(self.ty.clone()),
+            params: self.params.clone(),
+        }
+    }
+}
+
+/// An impl block applied to one struct instance.
+struct ImplInstance {
+    class_name: String,
+    self_type: Type,
+    base: String,
+    subst: TypeSubst,
+}
+
+/// Ring name prefixes: `prefix_structname_` per struct instance and
+/// `prefix_traitname_` per trait, honoring `#[ring(name)]`.
+struct RingNames {
+    prefix: String,
+    structs: HashMap<String, Vec<StructInstance>>,
+    skipped_structs: HashSet<String>,
+    trait_bases: HashMap<String, String>,
+}
+
+impl RingNames {
+    fn new(items: &[Item], prefix: &str) -> Self {
+        let mut structs = HashMap::new();
+        let mut skipped_structs = HashSet::new();
+        let mut trait_bases = HashMap::new();
+        for item in items {
+            match item {
+                Item::Struct(s) => {
+                    let attrs = ring_attrs(&s.attrs);
+                    let name = s.ident.to_string();
+                    if attrs.skip {
+                        skipped_structs.insert(name);
+                        continue;
+                    }
+                    let ident = &s.ident;
+                    let instances = if attrs.instantiate.is_empty() {
+                        let segment = attrs.name.unwrap_or_else(|| name.to_lowercase());
+                        vec![StructInstance {
+                            ty: syn::parse_quote!(#ident),
+                            base: format!("{}{}_", prefix, segment),
+                            class_name: name.clone(),
+                            params: Vec::new(),
+                        }]
+                    } else {
+                        let type_params: Vec<_> = s.generics.type_params().collect();
+                        attrs
+                            .instantiate
+                            .iter()
+                            .map(|entry| StructInstance {
+                                ty: entry.ty.clone(),
+                                base: format!("{}{}_", prefix, entry.name.value()),
+                                class_name: entry.name.value(),
+                                params: type_params
+                                    .iter()
+                                    .map(|p| p.ident.clone())
+                                    .zip(type_args(&entry.ty).into_iter().cloned())
+                                    .collect(),
+                            })
+                            .collect()
+                    };
+                    structs.insert(name, instances);
+                }
+                Item::Trait(t) if t.generics.params.is_empty() => {
+                    let attrs = ring_attrs(&t.attrs);
+                    if attrs.skip {
+                        continue;
+                    }
+                    let name = t.ident.to_string();
+                    let segment = attrs.name.unwrap_or_else(|| name.to_lowercase());
+                    trait_bases.insert(name, format!("{}{}_", prefix, segment));
+                }
+                _ => {}
+            }
+        }
+        RingNames {
+            prefix: prefix.to_string(),
+            structs,
+            skipped_structs,
+            trait_bases,
+        }
+    }
+
+    /// Whether `name` is a trait exported by this extension.
+    fn is_trait(&self, name: &str) -> bool {
+        self.trait_bases.contains_key(name)
+    }
+
+    fn trait_base(&self, trait_name: &str) -> Option<&str> {
+        self.trait_bases.get(trait_name).map(String::as_str)
+    }
+
+    /// Whether `name` is a struct exported by this extension.
+    fn is_struct(&self, name: &str) -> bool {
+        self.structs.contains_key(name)
+    }
+
+    /// Whether `ty` is an exported struct taken by value, not through a
+    /// reference.
+    fn is_by_value_struct(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Path(_))
+            && last_segment(ty).is_some_and(|s| self.is_struct(&s.ident.to_string()))
+    }
+
+    fn instances(&self, struct_name: &str) -> &[StructInstance] {
+        self.structs.get(struct_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The struct instance `ty` names, once `Self` and type parameters are
+    /// substituted.
+    fn instance_of(&self, ty: &Type) -> Option<&StructInstance> {
+        let segment = last_segment(ty)?;
+        let args = type_args(ty);
+        self.instances(&segment.ident.to_string())
+            .iter()
+            .find(|instance| {
+                let expected = type_args(&instance.ty);
+                expected.len() == args.len()
+                    && expected
+                        .iter()
+                        .zip(&args)
+                        .all(|(a, b)| quote!(#a).to_string() == quote!(#b).to_string())
+            })
+    }
+
+    /// The struct instances an impl block exports methods for, with their
+    /// bases. Empty if it is not exported: impls of traits defined outside
+    /// the extension (`Display`, `From`, ...) never are.
+    fn impl_instances(&self, i: &ItemImpl) -> Vec<ImplInstance> {
+        let Some(struct_name) = impl_self_name(i) else {
+            return Vec::new();
+        };
+        if impl_trait_name(i).is_some_and(|t| !self.is_trait(&t)) {
+            return Vec::new();
+        }
+        let attrs = ring_attrs(&i.attrs);
+        if attrs.skip || self.skipped_structs.contains(&struct_name) {
+            return Vec::new();
+        }
+        // A type defined outside the extension.
+        let outside;
+        let instances = match self.structs.get(&struct_name) {
+            Some(instances) => instances.as_slice(),
+            None => {
+                outside = [StructInstance {
+                    ty: (*i.self_ty).clone(),
+                    base: format!("{}{}_", self.prefix, struct_name.to_lowercase()),
+                    class_name: struct_name,
+                    params: Vec::new(),
+                }];
+                &outside[..]
+            }
+        };
+        instances
+            .iter()
+            .filter_map(|instance| {
+                Some(ImplInstance {
+                    class_name: instance.class_name.clone(),
+                    self_type: instance.ty.clone(),
+                    base: attrs
+                        .prefix
+                        .clone()
+                        .unwrap_or_else(|| instance.base.clone()),
+                    subst: impl_subst(i, instance)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// How `impl<..> Struct<..>` applies to `instance`: the impl's type
+/// parameters bound to the instance's types, or `None` when the impl is for
+/// other type arguments (`impl Stack<i32>` for a `Stack<f64>`).
+fn impl_subst(i: &ItemImpl, instance: &StructInstance) -> Option<TypeSubst> {
+    let impl_params: Vec<_> = i.generics.type_params().map(|p| &p.ident).collect();
+    let impl_args = type_args(&i.self_ty);
+    let instance_args = type_args(&instance.ty);
+    if impl_args.len() != instance_args.len() {
+        return None;
+    }
+    let mut subst = TypeSubst::for_self(&instance.ty);
+    for (arg, ty) in impl_args.into_iter().zip(instance_args) {
+        let param = match arg {
+            Type::Path(p) if p.qself.is_none() => p.path.get_ident(),
+            _ => None,
+        };
+        match param {
+            Some(param) if impl_params.contains(&param) => {
+                subst.params.push((param.clone(), ty.clone()));
+            }
+            _ if quote!(#arg).to_string() == quote!(#ty).to_string() => {}
+            _ => return None,
+        }
+    }
+    Some(subst)
+}
+
+/// The Ring name of a method: its impl's base plus `#[ring(name)]` or the
+/// Rust name.
+fn method_ring_name(base: &str, attrs: &[Attribute], ident: &Ident) -> String {
+    let segment = ring_attrs(attrs).name.unwrap_or_else(|| ident.to_string());
+    format!("{}{}", base, segment)
+}
+
 struct RingExtension {
     prefix: Option<String>,
     items: Vec<Item>,
@@ -160,6 +976,12 @@ impl Parse for RingExtension {
                     let _: Token![;] = input.parse()?;
                     prefix = Some(lit.value());
                     continue;
+                } else if ident == "classes" {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`classes:` is no longer supported; write the `RING_CLASSES` \
+                         constant to a file from a test or a build step instead",
+                    ));
                 } else {
                     return Err(syn::Error::new(ident.span(), "expected 'prefix' or item"));
                 }
@@ -174,63 +996,132 @@ impl Parse for RingExtension {
 /// Define a Ring module with auto-generated bindings and ring_libinit!
 #[proc_macro]
 pub fn ring_extension(input: TokenStream) -> TokenStream {
-    let module = parse_macro_input!(input as RingExtension);
+    let mut module = parse_macro_input!(input as RingExtension);
 
-    let prefix = module.prefix.unwrap_or_default();
+    let prefix = module.prefix.take().unwrap_or_default();
     let prefix_underscore = if prefix.is_empty() {
         String::new()
     } else {
         format!("{}_", prefix)
     };
 
+    if let Err(err) = validate_ring_attrs(&module.items) {
+        return err.to_compile_error().into();
+    }
+    inherit_trait_attrs(&mut module.items);
+    let names = RingNames::new(&module.items, &prefix_underscore);
+
     let mut structs_with_custom_new: HashSet<String> = HashSet::new();
     let mut impl_methods: HashSet<(String, String)> = HashSet::new();
 
+    // Keyed by the instance's class name, which is the struct name unless it
+    // is generic.
     for item in &module.items {
-        if let Item::Impl(i) = item {
-            if let Type::Path(p) = &*i.self_ty {
-                let struct_name = p.path.segments.last().unwrap().ident.to_string();
-                for impl_item in &i.items {
-                    if let ImplItem::Fn(method) = impl_item {
-                        let method_name = method.sig.ident.to_string();
-                        if method_name == "new" {
-                            structs_with_custom_new.insert(struct_name.clone());
-                        }
-                        impl_methods.insert((struct_name.clone(), method_name));
+        let Item::Impl(i) = item else { continue };
+        for instance in names.impl_instances(i) {
+            for impl_item in &i.items {
+                if let ImplItem::Fn(method) = impl_item {
+                    if ring_attrs(&method.attrs).skip {
+                        continue;
                     }
+                    let method_name = method.sig.ident.to_string();
+                    if method_name == "new" {
+                        structs_with_custom_new.insert(instance.class_name.clone());
+                    }
+                    impl_methods.insert((instance.class_name.clone(), method_name));
                 }
             }
         }
     }
 
+    // Exported struct instances implementing each exported trait, in source
+    // order.
+    let mut implementors: HashMap<String, Vec<Type>> = HashMap::new();
+    for item in &module.items {
+        let Item::Impl(i) = item else { continue };
+        let (Some(trait_name), Some(struct_name)) = (impl_trait_name(i), impl_self_name(i)) else {
+            continue;
+        };
+        if names.is_trait(&trait_name) && names.is_struct(&struct_name) {
+            implementors.entry(trait_name).or_default().extend(
+                names
+                    .impl_instances(i)
+                    .into_iter()
+                    .map(|inst| inst.self_type),
+            );
+        }
+    }
+
+    let ring_classes = generate_ring_classes(&module.items, &names, &impl_methods);
+
     let mut original_items = Vec::new();
     let mut generated_code = Vec::new();
     let mut registrations: Vec<(String, syn::Ident)> = Vec::new();
+    let mut globals: Vec<TokenStream2> = Vec::new();
 
     for item in module.items {
+        let stripped = strip_item(&item);
         match item {
-            Item::Struct(s) => {
-                let has_custom_new = structs_with_custom_new.contains(&s.ident.to_string());
-                let (orig, generated, regs) =
-                    process_struct(&s, &prefix_underscore, has_custom_new, &impl_methods);
+            Item::Struct(s) if !ring_attrs(&s.attrs).skip => {
+                let instances = names.instances(&s.ident.to_string());
+                let (orig, generated, regs) = process_struct(
+                    &s,
+                    instances,
+                    &structs_with_custom_new,
+                    &impl_methods,
+                    &names,
+                );
                 original_items.push(orig);
                 generated_code.push(generated);
                 registrations.extend(regs);
             }
-            Item::Impl(i) => {
-                let (orig, generated, regs) = process_impl(&i, &prefix_underscore);
+            Item::Impl(i) if !names.impl_instances(&i).is_empty() => {
+                let (orig, generated, regs) = process_impl(&i, &names.impl_instances(&i), &names);
                 original_items.push(orig);
                 generated_code.push(generated);
                 registrations.extend(regs);
             }
-            Item::Fn(f) => {
-                let (orig, generated, regs) = process_function(&f, &prefix_underscore);
+            Item::Trait(t) if names.is_trait(&t.ident.to_string()) => {
+                let base = names.trait_base(&t.ident.to_string()).unwrap_or_default();
+                let types = implementors
+                    .get(&t.ident.to_string())
+                    .map_or(&[][..], Vec::as_slice);
+                let (generated, regs) = process_trait(&t, base, types, &names);
+                original_items.push(quote! { #stripped });
+                generated_code.push(generated);
+                registrations.extend(regs);
+            }
+            Item::Fn(f) if !ring_attrs(&f.attrs).skip => {
+                let (orig, generated, regs) = process_function(&f, &prefix_underscore, &names);
+                original_items.push(orig);
+                generated_code.push(generated);
+                registrations.extend(regs);
+            }
+            Item::Enum(e) if e.generics.params.is_empty() => {
+                let (orig, generated, regs, values) = process_enum(&e, &prefix_underscore);
                 original_items.push(orig);
                 generated_code.push(generated);
                 registrations.extend(regs);
+                globals.extend(values);
+            }
+            Item::Const(c) if is_exported_constant(&c.vis, &c.attrs) => {
+                let (generated, reg, global) =
+                    process_constant(&c.ident, &c.ty, &c.attrs, &prefix_underscore);
+                original_items.push(quote! { #stripped });
+                generated_code.push(generated);
+                registrations.push(reg);
+                globals.push(global);
             }
-            other => {
-                original_items.push(quote! { #other });
+            Item::Static(s) if is_exported_constant(&s.vis, &s.attrs) => {
+                let (generated, reg, global) =
+                    process_constant(&s.ident, &s.ty, &s.attrs, &prefix_underscore);
+                original_items.push(quote! { #stripped });
+                generated_code.push(generated);
+                registrations.push(reg);
+                globals.push(global);
+            }
+            _ => {
+                original_items.push(quote! { #stripped });
             }
         }
     }
@@ -240,12 +1131,16 @@ pub fn ring_extension(input: TokenStream) -> TokenStream {
         .map(|(name, fn_ident)| {
             quote! { #name => #fn_ident }
         })
+        .chain(globals)
         .collect();
 
     let expanded = quote! {
         #(#original_items)*
         #(#generated_code)*
 
+        /// Ring classes wrapping the structs of this extension.
+        pub const RING_CLASSES: &str = #ring_classes;
+
         ring_libinit! {
             #(#libinit_entries),*
         }
@@ -254,688 +1149,1024 @@ pub fn ring_extension(input: TokenStream) -> TokenStream {
     expanded.into()
 }
 
-fn process_struct(
-    s: &ItemStruct,
-    prefix: &str,
-    has_custom_new: bool,
-    impl_methods: &HashSet<(String, String)>,
-) -> (TokenStream2, TokenStream2, Vec<(String, syn::Ident)>) {
-    let struct_name = &s.ident;
-    let struct_name_lower = struct_name.to_string().to_lowercase();
-    let type_const = format_ident!("{}_TYPE", struct_name.to_string().to_uppercase());
-    let type_const_str = format!("{}\0", struct_name);
-
-    let mut regs = Vec::new();
-
-    let delete_fn_name = format_ident!("ring_{}{}_delete", prefix, struct_name_lower);
-    let delete_ring_name = format!("{}{}_delete", prefix, struct_name_lower);
-    regs.push((delete_ring_name, delete_fn_name.clone()));
+#[rustfmt::skip]
+const RING_KEYWORDS: &[&str] = &[
+    "again", "and", "but", "bye", "call", "case", "catch", "class", "def", "do", "done", "else",
+    "elseif", "end", "exit", "for", "foreach", "from", "func", "function", "get", "give", "if",
+    "import", "in", "load", "loop", "new", "next", "not", "off", "ok", "on", "or", "other",
+    "package", "private", "put", "return", "see", "step", "switch", "to", "try", "while",
+    "endfunc", "endclass", "endpackage", "endif", "endfor", "endwhile", "endswitch", "endtry",
+    "endfunction", "break", "continue", "this", "self", "super", "main", "init", "operator",
+    "bracestart", "braceexpreval", "bracenewline", "braceerror", "braceend", "ringvm_see",
+    "ringvm_give", "ringvm_errorhandler", "changeringkeyword", "changeringoperator", "loadsyntax",
+];
+
+/// Same renaming as `RingSafeMethodName` in `tools/codegen/parsec.ring`.
+fn ring_safe_name(name: &str) -> String {
+    let lower = name.to_lowercase();
+    if !RING_KEYWORDS.contains(&lower.as_str()) {
+        return name.to_string();
+    }
+    match lower.as_str() {
+        "get" => "getValue".to_string(),
+        "put" => "putValue".to_string(),
+        "give" => "giveValue".to_string(),
+        "new" => "create".to_string(),
+        "see" => "show".to_string(),
+        "load" => "loadData".to_string(),
+        "call" => "invoke".to_string(),
+        "return" => "getReturn".to_string(),
+        _ => format!("{}_", name),
+    }
+}
 
-    let new_code = if !has_custom_new {
-        let new_fn_name = format_ident!("ring_{}{}_new", prefix, struct_name_lower);
-        let new_ring_name = format!("{}{}_new", prefix, struct_name_lower);
-        regs.push((new_ring_name, new_fn_name.clone()));
+fn typed_params(inputs: &syn::punctuated::Punctuated<FnArg, Token![,]>) -> Vec<(Ident, Type)> {
+    inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => match &*pat.pat {
+                Pat::Ident(ident) => Some((ident.ident.clone(), (*pat.ty).clone())),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
 
-        quote! {
-            ring_func!(#new_fn_name, |p| {
-                ring_check_paracount!(p, 0);
-                let obj = Box::new(#struct_name::default());
-                ring_ret_cpointer!(p, Box::into_raw(obj), #type_const);
-            });
-        }
+/// Ring expression passing `name` to a generated function. Struct and trait
+/// object arguments are unwrapped from their Ring class.
+fn ring_class_arg(name: &str, ty: &Type, names: &RingNames) -> String {
+    if pointer_struct_name(ty).is_some_and(|s| s == "Self" || names.is_struct(&s))
+        || dyn_trait_name(ty).is_some_and(|t| names.is_trait(&t))
+    {
+        format!("GetObjectPointerFromRingObject({})", name)
     } else {
-        quote! {}
-    };
+        name.to_string()
+    }
+}
 
-    let mut accessors = Vec::new();
-    let struct_name_str = struct_name.to_string();
+/// Ring expression returning the result of `call`. A struct, or a list of
+/// them, is wrapped in its Ring class.
+fn ring_class_ret(call: String, ty: &Type, subst: &TypeSubst, names: &RingNames) -> String {
+    match returned_instance(&ring_type(ty, subst), names) {
+        Some((instance, false)) => format!("{}fromPointer({})", instance.base, call),
+        Some((instance, true)) => format!("{}fromPointers({})", instance.base, call),
+        None => call,
+    }
+}
 
-    if let syn::Fields::Named(fields) = &s.fields {
+/// The struct instance a return type points to, and whether it is a list of
+/// them. Looks through references, `Option`, `Result` and `Box`.
+fn returned_instance<'a>(ty: &Type, names: &'a RingNames) -> Option<(&'a StructInstance, bool)> {
+    if let Type::Reference(r) = ty {
+        return returned_instance(&r.elem, names);
+    }
+    if let Some(inner) = option_inner(ty).or_else(|| result_ok_type(ty)) {
+        return returned_instance(inner, names);
+    }
+    let segment = last_segment(ty)?;
+    match (segment.ident.to_string().as_str(), type_args(ty).as_slice()) {
+        ("Box", [inner]) => returned_instance(inner, names),
+        ("Vec", [elem]) => match returned_instance(elem, names)? {
+            (instance, false) => Some((instance, true)),
+            (_, true) => None,
+        },
+        _ => names.instance_of(ty).map(|instance| (instance, false)),
+    }
+}
+
+/// Ring source with one class per struct, in the style of the classes
+/// `parsec.ring` generates: the C pointer lives in `pObject`, methods and field
+/// accessors forward to the generated functions. Returned structs are wrapped
+/// by a `prefix_struct_fromPointer()` function per class, defined before the
+/// classes as Ring requires.
+fn generate_ring_classes(
+    items: &[Item],
+    names: &RingNames,
+    impl_methods: &HashSet<(String, String)>,
+) -> String {
+    let mut code = String::new();
+    code.push_str("# Generated by ring_extension!\n");
+    code.push_str("# Do not edit manually\n\n");
+    code.push_str("load \"codegenlib.ring\"\n");
+
+    let structs: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) if !ring_attrs(&s.attrs).skip => Some(s),
+            _ => None,
+        })
+        .flat_map(|s| {
+            names
+                .instances(&s.ident.to_string())
+                .iter()
+                .map(move |instance| (s, instance))
+        })
+        .collect();
+    for (_, instance) in &structs {
+        let base = &instance.base;
+        code.push_str(&format!(
+            "\nFunc {base}fromPointer pointer\n\
+             \tif isNull(pointer)\n\t\treturn pointer\n\tok\n\
+             \toObject = new {}\n\toObject.pObject = pointer\n\treturn oObject\n",
+            instance.class_name
+        ));
+        code.push_str(&format!(
+            "\nFunc {base}fromPointers pointers\n\
+             \taObjects = []\n\
+             \tfor pointer in pointers\n\t\taObjects + {base}fromPointer(pointer)\n\tnext\n\
+             \treturn aObjects\n"
+        ));
+    }
+
+    for (s, instance) in structs {
+        let struct_name = s.ident.to_string();
+        let class_name = &instance.class_name;
+        let base = &instance.base;
+        let impls: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Impl(i) if impl_self_name(i).as_deref() == Some(struct_name.as_str()) => {
+                    names
+                        .impl_instances(i)
+                        .into_iter()
+                        .find(|impl_instance| impl_instance.class_name == *class_name)
+                        .map(|impl_instance| (i, impl_instance.base, impl_instance.subst))
+                }
+                _ => None,
+            })
+            .collect();
+        let methods: Vec<_> = impls
+            .iter()
+            .flat_map(|(i, base, subst)| {
+                i.items.iter().filter_map(move |item| match item {
+                    ImplItem::Fn(f) if is_exported_method(i, f) => Some((f, base, subst)),
+                    _ => None,
+                })
+            })
+            .collect();
+
+        code.push_str(&format!("\nClass {}\n\n\tpObject\n\n", class_name));
+
+        let custom_new = methods.iter().find(|(f, _, _)| f.sig.ident == "new");
+        let (ctor, ctor_params) = match custom_new {
+            Some((f, base, _)) => (
+                method_ring_name(base, &f.attrs, &f.sig.ident),
+                typed_params(&f.sig.inputs),
+            ),
+            None => (format!("{}new", base), vec![]),
+        };
+        let param_names: Vec<_> = ctor_params
+            .iter()
+            .map(|(name, _)| ring_safe_name(&name.to_string()))
+            .collect();
+        let args: Vec<_> = ctor_params
+            .iter()
+            .zip(&param_names)
+            .map(|((_, ty), name)| ring_class_arg(name, ty, names))
+            .collect();
+        if param_names.is_empty() {
+            code.push_str("\tFunc init()\n");
+        } else {
+            code.push_str(&format!("\tFunc init {}\n", param_names.join(", ")));
+        }
+        code.push_str(&format!(
+            "\t\tpObject = {}({})\n\t\treturn self\n\n",
+            ctor,
+            args.join(", ")
+        ));
+        code.push_str(&format!("\tFunc delete\n\t\t{}delete(pObject)\n\n", base));
+        code.push_str("\tFunc ObjectPointer\n\t\treturn pObject\n\n");
+
+        if let syn::Fields::Named(fields) = &s.fields {
+            for field in &fields.named {
+                let attrs = ring_attrs(&field.attrs);
+                if !matches!(field.vis, Visibility::Public(_)) || attrs.skip {
+                    continue;
+                }
+                let field_name = field.ident.as_ref().unwrap().to_string();
+                let segment = attrs.name.unwrap_or_else(|| field_name.clone());
+                let has =
+                    |method: &str| impl_methods.contains(&(class_name.clone(), method.to_string()));
+                if !has(&format!("get_{}", field_name)) && !has(&field_name) {
+                    let call = format!("{}get_{}(pObject)", base, segment);
+                    let call = if attrs.serde {
+                        call
+                    } else {
+                        ring_class_ret(call, &field.ty, &instance.subst(), names)
+                    };
+                    code.push_str(&format!(
+                        "\tFunc {}\n\t\treturn {}\n\n",
+                        ring_safe_name(&segment),
+                        call
+                    ));
+                }
+                if !attrs.readonly && !has(&format!("set_{}", field_name)) {
+                    let mut chars = segment.chars();
+                    let setter: String = chars
+                        .next()
+                        .into_iter()
+                        .flat_map(char::to_uppercase)
+                        .chain(chars)
+                        .collect();
+                    code.push_str(&format!(
+                        "\tFunc set{} value\n\t\t{}set_{}(pObject, {})\n\n",
+                        setter,
+                        base,
+                        segment,
+                        ring_class_arg("value", &field.ty, names)
+                    ));
+                }
+            }
+        }
+
+        for (method, method_base, subst) in &methods {
+            let has_self = method
+                .sig
+                .inputs
+                .iter()
+                .any(|arg| matches!(arg, FnArg::Receiver(_)));
+            if !has_self {
+                continue;
+            }
+            let ring_name = method_ring_name(method_base, &method.attrs, &method.sig.ident);
+            let segment = ring_attrs(&method.attrs)
+                .name
+                .unwrap_or_else(|| method.sig.ident.to_string());
+            let params = typed_params(&method.sig.inputs);
+            let param_names: Vec<_> = (1..=params.len()).map(|i| format!("P{}", i)).collect();
+            let args: Vec<_> = params
+                .iter()
+                .zip(&param_names)
+                .map(|((_, ty), name)| format!(", {}", ring_class_arg(name, ty, names)))
+                .collect();
+            let signature = if param_names.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", param_names.join(", "))
+            };
+            let call = format!("{}(pObject{})", ring_name, args.concat());
+            let call = match &method.sig.output {
+                syn::ReturnType::Type(_, ty) if !ring_attrs(&method.attrs).serde => {
+                    ring_class_ret(call, ty, subst, names)
+                }
+                _ => call,
+            };
+            code.push_str(&format!(
+                "\tFunc {}{}\n\t\treturn {}\n\n",
+                ring_safe_name(&segment),
+                signature,
+                call
+            ));
+        }
+    }
+
+    code
+}
+
+fn impl_self_name(i: &ItemImpl) -> Option<String> {
+    match &*i.self_ty {
+        Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The trait of an `impl Trait for Type` block.
+fn impl_trait_name(i: &ItemImpl) -> Option<String> {
+    let (_, path, _) = i.trait_.as_ref()?;
+    path.segments.last().map(|s| s.ident.to_string())
+}
+
+/// Methods of trait impls are exported without `pub`, like the trait's own.
+fn is_exported_method(i: &ItemImpl, f: &ImplItemFn) -> bool {
+    (i.trait_.is_some() || matches!(f.vis, Visibility::Public(_))) && !ring_attrs(&f.attrs).skip
+}
+
+/// The struct as written, plus pointer type, `_new`, `_delete` and field
+/// accessors for each of its instances.
+fn process_struct(
+    s: &ItemStruct,
+    instances: &[StructInstance],
+    custom_new: &HashSet<String>,
+    impl_methods: &HashSet<(String, String)>,
+    names: &RingNames,
+) -> (TokenStream2, TokenStream2, Vec<(String, syn::Ident)>) {
+    let mut regs = Vec::new();
+    let mut generated = Vec::new();
+
+    for instance in instances {
+        let has_custom_new = custom_new.contains(&instance.class_name);
+        let (code, instance_regs) =
+            generate_struct_instance(s, instance, has_custom_new, impl_methods, names);
+        generated.push(code);
+        regs.extend(instance_regs);
+    }
+
+    let original = strip_item(&Item::Struct(s.clone()));
+    let original = quote! { #original };
+
+    (original, quote! { #(#generated)* }, regs)
+}
+
+fn generate_struct_instance(
+    s: &ItemStruct,
+    instance: &StructInstance,
+    has_custom_new: bool,
+    impl_methods: &HashSet<(String, String)>,
+    names: &RingNames,
+) -> (TokenStream2, Vec<(String, syn::Ident)>) {
+    let self_type = &instance.ty;
+    let base = &instance.base;
+    let class_name = &instance.class_name;
+    let subst = instance.subst();
+    let type_const = format_ident!("{}_TYPE", class_name.to_uppercase());
+
+    let mut regs = Vec::new();
+
+    let delete_fn_name = format_ident!("ring_{}delete", base);
+    let delete_ring_name = format!("{}delete", base);
+    regs.push((delete_ring_name, delete_fn_name.clone()));
+
+    let new_code = if !has_custom_new {
+        let new_fn_name = format_ident!("ring_{}new", base);
+        let new_ring_name = format!("{}new", base);
+        regs.push((new_ring_name, new_fn_name.clone()));
+
+        quote! {
+            ring_func!(#new_fn_name, |p| {
+                ring_check_paracount!(p, 0);
+                ring_ret!(p, <#self_type>::default());
+            });
+        }
+    } else {
+        quote! {}
+    };
+
+    let mut accessors = Vec::new();
+
+    if let syn::Fields::Named(fields) = &s.fields {
         for field in &fields.named {
-            if !matches!(field.vis, Visibility::Public(_)) {
+            let field_attrs = ring_attrs(&field.attrs);
+            if !matches!(field.vis, Visibility::Public(_)) || field_attrs.skip {
                 continue;
             }
 
             let field_name = field.ident.as_ref().unwrap();
             let field_name_str = field_name.to_string();
             let field_type = &field.ty;
+            let field_segment = field_attrs
+                .name
+                .clone()
+                .unwrap_or_else(|| field_name_str.clone());
 
             let getter_method = format!("get_{}", field_name_str);
             let setter_method = format!("set_{}", field_name_str);
 
-            if !impl_methods.contains(&(struct_name_str.clone(), getter_method.clone()))
-                && !impl_methods.contains(&(struct_name_str.clone(), field_name_str.clone()))
+            if !impl_methods.contains(&(class_name.clone(), getter_method.clone()))
+                && !impl_methods.contains(&(class_name.clone(), field_name_str.clone()))
             {
-                let getter_fn =
-                    format_ident!("ring_{}{}_get_{}", prefix, struct_name_lower, field_name);
-                let getter_name = format!("{}{}_get_{}", prefix, struct_name_lower, field_name);
+                let getter_fn = format_ident!("ring_{}get_{}", base, field_segment);
+                let getter_name = format!("{}get_{}", base, field_segment);
                 regs.push((getter_name, getter_fn.clone()));
 
-                let getter_code = generate_field_getter(
-                    &getter_fn,
-                    struct_name,
-                    &type_const,
-                    field_name,
-                    field_type,
-                );
+                let getter_code = if field_attrs.view {
+                    generate_field_view(&getter_fn, &subst, field_name, field_type)
+                } else {
+                    generate_field_getter(
+                        &getter_fn,
+                        &subst,
+                        field_name,
+                        field_type,
+                        field_attrs.passing(),
+                        names,
+                    )
+                };
                 accessors.push(getter_code);
             }
 
-            if !impl_methods.contains(&(struct_name_str.clone(), setter_method)) {
-                let setter_fn =
-                    format_ident!("ring_{}{}_set_{}", prefix, struct_name_lower, field_name);
-                let setter_name = format!("{}{}_set_{}", prefix, struct_name_lower, field_name);
+            if !field_attrs.readonly && !impl_methods.contains(&(class_name.clone(), setter_method))
+            {
+                let setter_fn = format_ident!("ring_{}set_{}", base, field_segment);
+                let setter_name = format!("{}set_{}", base, field_segment);
                 regs.push((setter_name, setter_fn.clone()));
 
                 let setter_code = generate_field_setter(
                     &setter_fn,
-                    struct_name,
-                    &type_const,
+                    &subst,
                     field_name,
                     field_type,
+                    field_attrs.passing(),
+                    names,
                 );
                 accessors.push(setter_code);
             }
         }
     }
 
-    let original = quote! { #s };
+    // Instances of generic structs are tagged with their Ring name.
+    let pointer_type = if instance.params.is_empty() {
+        quote! { ring_pointer_type!(#self_type); }
+    } else {
+        quote! { ring_pointer_type!(#self_type, #class_name); }
+    };
 
     let generated = quote! {
-        const #type_const: &[u8] = #type_const_str.as_bytes();
+        #pointer_type
+
+        const #type_const: &[u8] = <#self_type as RingPointer>::RING_TYPE;
 
         #new_code
 
         ring_func!(#delete_fn_name, |p| {
             ring_check_paracount!(p, 1);
-            ring_check_cpointer!(p, 1);
-            let ptr = ring_get_cpointer!(p, 1, #type_const);
-            if !ptr.is_null() {
-                unsafe { let _ = Box::from_raw(ptr as *mut #struct_name); }
-            }
+            let obj: *mut #self_type = ring_get!(p, 1, &mut #self_type);
+            // Null it first so other references raise an error, and the GC's
+            // free function skips it. A view only releases its parent.
+            ring_api_setnullpointer(p, 1);
+            ring_pointer_drop(obj);
         });
 
         #(#accessors)*
     };
 
-    (original, generated, regs)
+    (generated, regs)
 }
 
-fn process_impl(
-    i: &ItemImpl,
+fn process_enum(
+    e: &ItemEnum,
     prefix: &str,
-) -> (TokenStream2, TokenStream2, Vec<(String, syn::Ident)>) {
-    let struct_name = match &*i.self_ty {
-        Type::Path(p) => p.path.segments.last().unwrap().ident.clone(),
-        _ => return (quote! { #i }, quote! {}, vec![]),
-    };
+) -> (
+    TokenStream2,
+    TokenStream2,
+    Vec<(String, syn::Ident)>,
+    Vec<TokenStream2>,
+) {
+    let attrs = ring_attrs(&e.attrs);
+    let enum_name_lower = attrs
+        .name
+        .unwrap_or_else(|| e.ident.to_string().to_lowercase());
+    let is_c_like = e
+        .variants
+        .iter()
+        .all(|v| matches!(v.fields, syn::Fields::Unit));
+
+    let original = strip_item(&Item::Enum(e.clone()));
+    let original = quote! { #original };
+    if is_c_like {
+        let (generated, regs, globals) = generate_c_like_enum(e, prefix, &enum_name_lower);
+        if attrs.skip {
+            // Conversions only: the enum still works as a parameter or return type.
+            return (original, generated, vec![], vec![]);
+        }
+        (original, generated, regs, globals)
+    } else {
+        (original, generate_data_enum(e), vec![], vec![])
+    }
+}
 
-    let struct_name_lower = struct_name.to_string().to_lowercase();
-    let type_const = format_ident!("{}_TYPE", struct_name.to_string().to_uppercase());
+/// C-like enums are numbers in Ring: each variant becomes the global
+/// `prefix_enum_variant`, set when the library loads, and
+/// `prefix_enum_name(value)` returns the variant name. Parameters accept the
+/// number or the variant name. A `prefix_enum_variant()` function returning
+/// the same value is registered as well, for scripts written before the
+/// globals existed. Returns the `ring_libinit!` entries of the globals last.
+fn generate_c_like_enum(
+    e: &ItemEnum,
+    prefix: &str,
+    enum_name_lower: &str,
+) -> (TokenStream2, Vec<(String, syn::Ident)>, Vec<TokenStream2>) {
+    let enum_name = &e.ident;
+    let enum_name_str = enum_name.to_string();
+    let variants: Vec<_> = e.variants.iter().map(|v| &v.ident).collect();
+    let variant_strs: Vec<_> = variants.iter().map(|v| v.to_string()).collect();
+    let variant_lower: Vec<_> = variant_strs.iter().map(|v| v.to_lowercase()).collect();
 
     let mut regs = Vec::new();
-    let mut method_wrappers = Vec::new();
+    let mut constants = Vec::new();
+    let mut globals = Vec::new();
+    for (v, variant) in e.variants.iter().zip(&variants) {
+        let segment = ring_attrs(&v.attrs)
+            .name
+            .unwrap_or_else(|| variant.to_string().to_lowercase());
+        let ring_name = format!("{}{}_{}", prefix, enum_name_lower, segment);
+        let fn_name = format_ident!("ring_{}", ring_name);
+        globals.push(quote! { #ring_name = #enum_name::#variant as i64 as f64 });
+        regs.push((ring_name, fn_name.clone()));
+        constants.push(quote! {
+            ring_func!(#fn_name, |p| {
+                ring_check_paracount!(p, 0);
+                ring_ret_number!(p, #enum_name::#variant as i64 as f64);
+            });
+        });
+    }
 
-    for item in &i.items {
-        if let ImplItem::Fn(method) = item {
-            if !matches!(method.vis, Visibility::Public(_)) {
-                continue;
+    let name_fn = format_ident!("ring_{}{}_name", prefix, enum_name_lower);
+    regs.push((
+        format!("{}{}_name", prefix, enum_name_lower),
+        name_fn.clone(),
+    ));
+
+    let generated = quote! {
+        impl<'a> FromRing<'a> for #enum_name {
+            fn from_item(item: &'a Item) -> RingResult<Self> {
+                if let Ok(name) = <&str as FromRing>::from_item(item) {
+                    return match name.to_ascii_lowercase().as_str() {
+                        #(#variant_lower => Ok(#enum_name::#variants),)*
+                        _ => Err(RingError::new(format!(
+                            "unknown {} variant: {}", #enum_name_str, name
+                        ))),
+                    };
+                }
+                let value = <i64 as FromRing>::from_item(item)?;
+                #(
+                    if value == #enum_name::#variants as i64 {
+                        return Ok(#enum_name::#variants);
+                    }
+                )*
+                Err(RingError::new(format!(
+                    "invalid {} value: {}", #enum_name_str, value
+                )))
+            }
+        }
+
+        impl IntoRing for #enum_name {
+            fn into_item(self, item: RingItem) {
+                ring_item_setdouble(item, self as i64 as f64);
             }
+        }
 
-            let method_name = &method.sig.ident;
-            let method_name_str = method_name.to_string();
+        #(#constants)*
 
-            if method_name_str == "new" {
-                let (code, name, fn_ident) = generate_custom_new(
-                    &struct_name,
-                    &struct_name_lower,
-                    &type_const,
-                    method,
-                    prefix,
-                );
-                method_wrappers.push(code);
-                regs.push((name, fn_ident));
+        ring_func!(#name_fn, |p| {
+            ring_check_paracount!(p, 1);
+            let value = ring_get!(p, 1, #enum_name);
+            let name = match value {
+                #(#enum_name::#variants => #variant_strs,)*
+            };
+            ring_ret_string!(p, name);
+        });
+    };
+
+    (generated, regs, globals)
+}
+
+/// Enums with data are tagged lists in Ring: the variant name followed by its
+/// fields in declaration order, e.g. `["Circle", 2.5]`. A unit variant may
+/// also be passed as just its name.
+fn generate_data_enum(e: &ItemEnum) -> TokenStream2 {
+    let enum_name = &e.ident;
+    let enum_name_str = enum_name.to_string();
+
+    let mut unit_arms = Vec::new();
+    let mut list_arms = Vec::new();
+    let mut into_arms = Vec::new();
+
+    for v in &e.variants {
+        let variant = &v.ident;
+        let variant_str = variant.to_string();
+        let lower = variant_str.to_lowercase();
+        let field_count = v.fields.len();
+        let expected_len = field_count + 1;
+        let indexes: Vec<_> = (1..=field_count).collect();
+        let bindings: Vec<_> = (0..field_count)
+            .map(|i| format_ident!("__f{}", i))
+            .collect();
+
+        let (construct, pattern) = match &v.fields {
+            syn::Fields::Unit => {
+                unit_arms.push(quote! { #lower => return Ok(#enum_name::#variant), });
+                (
+                    quote! { #enum_name::#variant },
+                    quote! { #enum_name::#variant },
+                )
+            }
+            syn::Fields::Unnamed(_) => (
+                quote! { #enum_name::#variant(#(__list.get_as(#indexes)?),*) },
+                quote! { #enum_name::#variant(#(#bindings),*) },
+            ),
+            syn::Fields::Named(fields) => {
+                let names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                (
+                    quote! { #enum_name::#variant { #(#names: __list.get_as(#indexes)?),* } },
+                    quote! { #enum_name::#variant { #(#names: #bindings),* } },
+                )
+            }
+        };
+
+        list_arms.push(quote! {
+            #lower => {
+                if __list.len() != #expected_len {
+                    return Err(RingError::new(format!(
+                        "{}::{} expects {} values, found {}",
+                        #enum_name_str, #variant_str, #field_count, __list.len() - 1
+                    )));
+                }
+                Ok(#construct)
+            }
+        });
+        into_arms.push(quote! {
+            #pattern => {
+                __list.push(#variant_str);
+                #(__list.push(#bindings);)*
+            }
+        });
+    }
+
+    quote! {
+        impl<'a> FromRing<'a> for #enum_name {
+            fn from_item(item: &'a Item) -> RingResult<Self> {
+                if let Ok(name) = <&str as FromRing>::from_item(item) {
+                    match name.to_ascii_lowercase().as_str() {
+                        #(#unit_arms)*
+                        _ => {
+                            return Err(RingError::new(format!(
+                                "unknown {} variant: {}", #enum_name_str, name
+                            )));
+                        }
+                    }
+                }
+                let __list = <ListRef as FromRing>::from_item(item)?;
+                let __tag: &str = __list.get_as(0)?;
+                match __tag.to_ascii_lowercase().as_str() {
+                    #(#list_arms)*
+                    _ => Err(RingError::new(format!(
+                        "unknown {} variant: {}", #enum_name_str, __tag
+                    ))),
+                }
+            }
+        }
+
+        impl IntoRing for #enum_name {
+            fn into_item(self, item: RingItem) {
+                let mut __list = ListMut::new_in_item(item);
+                match self {
+                    #(#into_arms)*
+                }
+            }
+        }
+    }
+}
+
+/// Wrappers for the methods of an impl block, once per struct instance it
+/// applies to.
+fn process_impl(
+    i: &ItemImpl,
+    instances: &[ImplInstance],
+    names: &RingNames,
+) -> (TokenStream2, TokenStream2, Vec<(String, syn::Ident)>) {
+    let original = strip_item(&Item::Impl(i.clone()));
+    let original = quote! { #original };
+
+    let mut regs = Vec::new();
+    let mut method_wrappers = Vec::new();
+
+    for instance in instances {
+        let subst = &instance.subst;
+        for item in &i.items {
+            let ImplItem::Fn(method) = item else { continue };
+            if !is_exported_method(i, method) {
                 continue;
             }
 
-            let has_self = method
-                .sig
-                .inputs
-                .iter()
-                .any(|arg| matches!(arg, FnArg::Receiver(_)));
+            let ring_name = method_ring_name(&instance.base, &method.attrs, &method.sig.ident);
 
-            if has_self {
-                let (code, name, fn_ident) = generate_method(
-                    &struct_name,
-                    &struct_name_lower,
-                    &type_const,
-                    method,
-                    prefix,
-                );
-                method_wrappers.push(code);
-                regs.push((name, fn_ident));
+            let (code, name, fn_ident) = if method.sig.ident == "new" {
+                generate_custom_new(subst, method, ring_name, names)
+            } else if method.sig.receiver().is_some() {
+                generate_method(subst, method, ring_name, names)
             } else {
-                let (code, name, fn_ident) = generate_static_method(
-                    &struct_name,
-                    &struct_name_lower,
-                    &type_const,
-                    method,
-                    prefix,
-                );
-                method_wrappers.push(code);
-                regs.push((name, fn_ident));
-            }
+                generate_static_method(subst, method, ring_name, names)
+            };
+            method_wrappers.push(code);
+            regs.push((name, fn_ident));
         }
     }
 
-    let original = quote! { #i };
     let generated = quote! { #(#method_wrappers)* };
 
     (original, generated, regs)
 }
 
+/// Trait-level functions: `prefix_trait_method(ptr, ...)` takes a pointer to
+/// any exported struct implementing the trait and calls the method through
+/// `&dyn Trait`, picking the type by the pointer's type name.
+///
+/// Only methods callable on a trait object are exported: a `&self` or
+/// `&mut self` receiver, no type parameters and no other use of `Self`.
+fn process_trait(
+    t: &ItemTrait,
+    base: &str,
+    implementors: &[Type],
+    names: &RingNames,
+) -> (TokenStream2, Vec<(String, syn::Ident)>) {
+    if implementors.is_empty() {
+        return (quote! {}, vec![]);
+    }
+    let trait_name = &t.ident;
+
+    let mut regs = Vec::new();
+    let mut method_wrappers = Vec::new();
+
+    for item in &t.items {
+        let TraitItem::Fn(method) = item else {
+            continue;
+        };
+        if ring_attrs(&method.attrs).skip {
+            continue;
+        }
+        let Some(obj_type) = dyn_receiver_type(trait_name, &method.sig) else {
+            continue;
+        };
+        let obj = quote! { ring_get!(p, 1, #obj_type) };
+        let ring_name = method_ring_name(base, &method.attrs, &method.sig.ident);
+        let passing = ring_attrs(&method.attrs).passing();
+        let (code, name, fn_ident) = generate_receiver_method(
+            &method.sig,
+            obj,
+            &TypeSubst::default(),
+            passing,
+            ring_name,
+            names,
+        );
+        method_wrappers.push(code);
+        regs.push((name, fn_ident));
+    }
+
+    let dyn_impl = quote_spanned! {trait_name.span()=>
+        ring_pointer_dyn!(#trait_name: #(#implementors),*);
+    };
+    let generated = quote! {
+        #dyn_impl
+        #(#method_wrappers)*
+    };
+
+    (generated, regs)
+}
+
+/// `&dyn Trait` or `&mut dyn Trait` for a method callable on a trait object.
+fn dyn_receiver_type(trait_name: &Ident, sig: &syn::Signature) -> Option<TokenStream2> {
+    let receiver = sig.receiver()?;
+    if receiver.reference.is_none()
+        || sig
+            .generics
+            .params
+            .iter()
+            .any(|p| !matches!(p, syn::GenericParam::Lifetime(_)))
+        || sig.generics.where_clause.is_some()
+    {
+        return None;
+    }
+    let mentions_self = |tokens: TokenStream2| {
+        tokens
+            .to_string()
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .any(|w| w == "Self")
+    };
+    let params = sig
+        .inputs
+        .iter()
+        .filter(|arg| matches!(arg, FnArg::Typed(_)));
+    if params.clone().any(|arg| mentions_self(quote! { #arg })) {
+        return None;
+    }
+    let output = &sig.output;
+    if mentions_self(quote! { #output }) {
+        return None;
+    }
+    Some(if receiver.mutability.is_some() {
+        quote! { &mut dyn #trait_name }
+    } else {
+        quote! { &dyn #trait_name }
+    })
+}
+
+/// Validation already rejected `#[ring(export)]` on private and `static mut`
+/// items.
+fn is_exported_constant(vis: &Visibility, attrs: &[Attribute]) -> bool {
+    matches!(vis, Visibility::Public(_)) && ring_attrs(attrs).export
+}
+
+/// A `#[ring(export)]` const or static becomes the Ring global `prefix_name`, set
+/// when the library loads, and the function `prefix_get_name()`, which always
+/// returns the Rust value. Returns the getter, its registration and the
+/// `ring_libinit!` entry of the global.
+fn process_constant(
+    ident: &Ident,
+    ty: &Type,
+    attrs: &[Attribute],
+    prefix: &str,
+) -> (TokenStream2, (String, syn::Ident), TokenStream2) {
+    let segment = ring_attrs(attrs)
+        .name
+        .unwrap_or_else(|| ident.to_string().to_lowercase());
+    let getter_name = format!("{}get_{}", prefix, segment);
+    let fn_name = format_ident!("ring_{}", getter_name);
+    let global_name = format!("{}{}", prefix, segment);
+    // Spanned at the type, so one without an `IntoRing` impl is reported there.
+    let value = quote_spanned! {ty.span()=> &#ident };
+    let getter = quote! {
+        ring_func!(#fn_name, |p| {
+            ring_check_paracount!(p, 0);
+            ring_ret!(p, #value);
+        });
+    };
+    (
+        getter,
+        (getter_name, fn_name),
+        quote! { #global_name = #value },
+    )
+}
+
+/// A generic fn gets one wrapper per `#[ring(instantiate)]` entry, calling it
+/// with those type arguments.
 fn process_function(
     f: &ItemFn,
     prefix: &str,
+    names: &RingNames,
 ) -> (TokenStream2, TokenStream2, Vec<(String, syn::Ident)>) {
     let fn_name = &f.sig.ident;
-    let ring_fn_name = format_ident!("ring_{}{}", prefix, fn_name);
-    let ring_name = format!("{}{}", prefix, fn_name);
-
-    let params: Vec<_> = f
-        .sig
-        .inputs
-        .iter()
-        .filter_map(|arg| {
-            if let FnArg::Typed(pat) = arg {
-                let name = if let Pat::Ident(ident) = &*pat.pat {
-                    ident.ident.clone()
-                } else {
-                    return None;
-                };
-                Some((name, (*pat.ty).clone()))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    let param_count = params.len();
-    let mut checks = Vec::new();
-    let mut gets = Vec::new();
-    let mut args = Vec::new();
+    let attrs = ring_attrs(&f.attrs);
+
+    let original = strip_item(&Item::Fn(f.clone()));
+    let original = quote! { #original };
+
+    if attrs.instantiate.is_empty() {
+        let segment = attrs.name.unwrap_or_else(|| fn_name.to_string());
+        let ring_name = format!("{}{}", prefix, segment);
+        let (generated, reg) = generate_function(
+            f,
+            ring_name,
+            quote! { #fn_name },
+            &TypeSubst::default(),
+            names,
+        );
+        return (original, generated, vec![reg]);
+    }
 
-    for (i, (name, ty)) in params.iter().enumerate() {
-        let idx = (i + 1) as i32;
-        let binding = generate_param_binding(name, ty, idx);
-        checks.push(binding.check);
-        gets.push(binding.get);
-        args.push(binding.arg);
+    let type_params: Vec<_> = f.sig.generics.type_params().map(|p| &p.ident).collect();
+    let mut generated = Vec::new();
+    let mut regs = Vec::new();
+    for entry in &attrs.instantiate {
+        let type_args = type_args(&entry.ty);
+        let subst = TypeSubst {
+            self_type: None,
+            params: type_params
+                .iter()
+                .map(|p| (*p).clone())
+                .zip(type_args.iter().map(|ty| (*ty).clone()))
+                .collect(),
+        };
+        let ring_name = format!("{}{}", prefix, entry.name.value());
+        let path = quote! { #fn_name::<#(#type_args),*> };
+        let (code, reg) = generate_function(f, ring_name, path, &subst, names);
+        generated.push(code);
+        regs.push(reg);
     }
 
-    let param_count_i32 = param_count as i32;
-    let return_code = generate_return_code(&f.sig.output, quote! { #fn_name(#(#args),*) });
+    (original, quote! { #(#generated)* }, regs)
+}
+
+fn generate_function(
+    f: &ItemFn,
+    ring_name: String,
+    path: TokenStream2,
+    subst: &TypeSubst,
+    names: &RingNames,
+) -> (TokenStream2, (String, syn::Ident)) {
+    let ring_fn_name = format_ident!("ring_{}", ring_name);
+
+    let ParamBindings {
+        gets,
+        args,
+        checks,
+        count_check,
+    } = generate_param_bindings(&f.sig.inputs, 1, subst, names);
+
+    let return_code = generate_return_code(
+        &f.sig.output,
+        checked_call(quote! { #path(#(#args),*) }, &checks),
+        subst,
+        ring_attrs(&f.attrs).passing(),
+    );
 
-    let original = quote! { #f };
     let generated = quote! {
         ring_func!(#ring_fn_name, |p| {
-            ring_check_paracount!(p, #param_count_i32);
-            #(#checks)*
+            #count_check
             #(#gets)*
             #return_code
         });
     };
 
-    (original, generated, vec![(ring_name, ring_fn_name)])
+    (generated, (ring_name, ring_fn_name))
 }
 
 fn generate_field_getter(
     fn_name: &syn::Ident,
-    struct_name: &syn::Ident,
-    type_const: &syn::Ident,
+    subst: &TypeSubst,
     field_name: &syn::Ident,
     field_type: &Type,
+    passing: Passing,
+    names: &RingNames,
 ) -> TokenStream2 {
-    let type_str = quote!(#field_type).to_string();
-    let return_expr = if is_number_type(&type_str) {
-        quote! { ring_ret_number!(p, obj.#field_name as f64); }
-    } else if is_string_type(&type_str) {
-        quote! { ring_ret_string!(p, &obj.#field_name); }
-    } else if type_str == "bool" {
-        quote! { ring_ret_number!(p, if obj.#field_name { 1.0 } else { 0.0 }); }
-    } else if is_vec_type(&type_str) {
-        let inner = extract_vec_inner(&type_str).unwrap_or_default();
-        if is_number_type(&inner) {
-            quote! {
-                let __list = ring_new_list!(p);
-                for __item in &obj.#field_name {
-                    ring_list_adddouble(__list, *__item as f64);
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else if is_string_type(&inner) {
-            quote! {
-                let __list = ring_new_list!(p);
-                for __item in &obj.#field_name {
-                    ring_list_addstring_str(__list, &__item);
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else if is_struct_type(&inner) {
-            let inner_type_const = struct_type_const(&extract_struct_name(&inner));
-            quote! {
-                let __list = ring_new_list!(p);
-                for __item in &obj.#field_name {
-                    let __ptr = Box::into_raw(Box::new(__item.clone()));
-                    ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #inner_type_const);
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else {
-            quote! {
-                let __list = ring_new_list!(p);
-                for __item in &obj.#field_name {
-                    ring_list_adddouble(__list, *__item as f64);
-                }
-                ring_ret_list!(p, __list);
-            }
-        }
-    } else if is_option_type(&type_str) {
-        let inner = extract_option_inner(&type_str).unwrap_or_default();
-        if is_number_type(&inner) {
-            quote! {
-                match &obj.#field_name {
-                    Some(__val) => ring_ret_number!(p, *__val as f64),
-                    None => {},
-                }
-            }
-        } else if is_string_type(&inner) {
-            quote! {
-                match &obj.#field_name {
-                    Some(__val) => ring_ret_string!(p, __val),
-                    None => {},
-                }
-            }
-        } else if is_struct_type(&inner) {
-            let inner_type_const = struct_type_const(&extract_struct_name(&inner));
-            quote! {
-                match &obj.#field_name {
-                    Some(__val) => {
-                        ring_ret_cpointer!(p, Box::into_raw(Box::new(__val.clone())), #inner_type_const);
-                    }
-                    None => {},
-                }
-            }
-        } else {
-            quote! {
-                match &obj.#field_name {
-                    Some(__val) => ring_ret_number!(p, *__val as f64),
-                    None => {},
-                }
-            }
-        }
-    } else if is_struct_type(&type_str) {
-        let field_type_const = struct_type_const(&extract_struct_name(&type_str));
-        quote! {
-            ring_ret_cpointer!(p, Box::into_raw(Box::new(obj.#field_name.clone())), #field_type_const);
+    let self_type = subst.self_type();
+    let field_type = ring_type(field_type, subst);
+    let value = if passing == Passing::Serde {
+        quote_spanned! {field_type.span()=> RingSerde(&obj.#field_name) }
+    } else if names.is_by_value_struct(&field_type) {
+        quote_spanned! {field_type.span()=>
+            <#field_type as RingByValue>::ring_by_value(&obj.#field_name)
         }
     } else {
-        quote! { ring_ret_number!(p, obj.#field_name as f64); }
+        byte_buffer_ret(&field_type, quote! { obj.#field_name }, passing)
+            .unwrap_or_else(|| quote! { &obj.#field_name })
     };
-
+    let ret = quote_spanned! {field_type.span()=> ring_ret!(p, #value) };
     quote! {
         ring_func!(#fn_name, |p| {
             ring_check_paracount!(p, 1);
-            ring_check_cpointer!(p, 1);
-            if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                #return_expr
-            } else {
-                ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-            }
+            let obj = ring_get!(p, 1, &#self_type);
+            #ret;
         });
     }
 }
 
-fn generate_field_setter(
+/// A `#[ring(view)]` getter: a pointer into the parent, which it keeps alive.
+fn generate_field_view(
     fn_name: &syn::Ident,
-    struct_name: &syn::Ident,
-    type_const: &syn::Ident,
+    subst: &TypeSubst,
     field_name: &syn::Ident,
     field_type: &Type,
 ) -> TokenStream2 {
-    let type_str = quote!(#field_type).to_string();
+    let self_type = subst.self_type();
+    let field_type = ring_type(field_type, subst);
+    let ret = quote_spanned! {field_type.span()=> ring_pointer_view_ret(p, obj, field) };
+    quote! {
+        ring_func!(#fn_name, |p| {
+            ring_check_paracount!(p, 1);
+            let obj = ring_get!(p, 1, &mut #self_type);
+            let field: *mut #field_type = &mut obj.#field_name;
+            #ret;
+        });
+    }
+}
 
-    if is_number_type(&type_str) {
-        let cast = get_number_cast(&type_str);
-        quote! {
-            ring_func!(#fn_name, |p| {
-                ring_check_paracount!(p, 2);
-                ring_check_cpointer!(p, 1);
-                ring_check_number!(p, 2);
-                if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                    obj.#field_name = ring_get_number!(p, 2) as #cast;
-                } else {
-                    ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                }
-            });
-        }
-    } else if is_string_type(&type_str) {
-        quote! {
-            ring_func!(#fn_name, |p| {
-                ring_check_paracount!(p, 2);
-                ring_check_cpointer!(p, 1);
-                ring_check_string!(p, 2);
-                if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                    obj.#field_name = ring_get_string!(p, 2).to_string();
-                } else {
-                    ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                }
-            });
-        }
-    } else if type_str == "bool" {
-        quote! {
-            ring_func!(#fn_name, |p| {
-                ring_check_paracount!(p, 2);
-                ring_check_cpointer!(p, 1);
-                ring_check_number!(p, 2);
-                if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                    obj.#field_name = ring_get_number!(p, 2) != 0.0;
-                } else {
-                    ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                }
-            });
-        }
-    } else if is_vec_type(&type_str) {
-        let inner = extract_vec_inner(&type_str).unwrap_or_default();
-        if is_number_type(&inner) {
-            let cast = get_number_cast(&inner);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    ring_check_list!(p, 2);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        let __list = ring_get_list!(p, 2);
-                        let __size = ring_list_getsize(__list);
-                        let mut __vec = Vec::with_capacity(__size as usize);
-                        for __i in 1..=__size {
-                            if ring_list_isnumber(__list, __i) {
-                                __vec.push(ring_list_getdouble(__list, __i) as #cast);
-                            }
-                        }
-                        obj.#field_name = __vec;
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else if is_string_type(&inner) {
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    ring_check_list!(p, 2);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        let __list = ring_get_list!(p, 2);
-                        let __size = ring_list_getsize(__list);
-                        let mut __vec = Vec::with_capacity(__size as usize);
-                        for __i in 1..=__size {
-                            if ring_list_isstring(__list, __i) {
-                                let __cstr = ring_list_getstring(__list, __i);
-                                let __s = unsafe { std::ffi::CStr::from_ptr(__cstr).to_string_lossy().into_owned() };
-                                __vec.push(__s);
-                            }
-                        }
-                        obj.#field_name = __vec;
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else if is_struct_type(&inner) {
-            let inner_struct_name = extract_struct_name(&inner);
-            let inner_struct_ident = format_ident!("{}", inner_struct_name);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    ring_check_list!(p, 2);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        let __list = ring_get_list!(p, 2);
-                        let __size = ring_list_getsize(__list);
-                        let mut __vec = Vec::with_capacity(__size as usize);
-                        for __i in 1..=__size {
-                            let __ptr = if ring_list_ispointer(__list, __i) {
-                                ring_list_getpointer(__list, __i)
-                            } else if ring_list_islist(__list, __i) {
-                                let __inner_list = ring_list_getlist(__list, __i);
-                                if ring_list_ispointer(__inner_list, 1) {
-                                    ring_list_getpointer(__inner_list, 1)
-                                } else {
-                                    std::ptr::null_mut()
-                                }
-                            } else {
-                                std::ptr::null_mut()
-                            };
-                            if !__ptr.is_null() {
-                                let __item = unsafe { &*(__ptr as *const #inner_struct_ident) };
-                                __vec.push(__item.clone());
-                            }
-                        }
-                        obj.#field_name = __vec;
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else {
-            let cast = get_number_cast(&inner);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    ring_check_list!(p, 2);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        let __list = ring_get_list!(p, 2);
-                        let __size = ring_list_getsize(__list);
-                        let mut __vec = Vec::with_capacity(__size as usize);
-                        for __i in 1..=__size {
-                            if ring_list_isnumber(__list, __i) {
-                                __vec.push(ring_list_getdouble(__list, __i) as #cast);
-                            }
-                        }
-                        obj.#field_name = __vec;
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        }
-    } else if is_option_type(&type_str) {
-        let inner = extract_option_inner(&type_str).unwrap_or_default();
-        if is_number_type(&inner) {
-            let cast = get_number_cast(&inner);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        if ring_api_isstring(p, 2) {
-                            let __s = ring_get_string!(p, 2);
-                            if __s.is_empty() {
-                                obj.#field_name = None;
-                            } else {
-                                ring_error!(p, "Expected number or empty string for Option");
-                            }
-                        } else if ring_api_isnumber(p, 2) {
-                            obj.#field_name = Some(ring_get_number!(p, 2) as #cast);
-                        } else {
-                            ring_error!(p, "Expected number or empty string for Option");
-                        }
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else if is_string_type(&inner) {
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    ring_check_string!(p, 2);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        let __s = ring_get_string!(p, 2);
-                        if __s.is_empty() {
-                            obj.#field_name = None;
-                        } else {
-                            obj.#field_name = Some(__s.to_string());
-                        }
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else if is_struct_type(&inner) {
-            let inner_struct_name = extract_struct_name(&inner);
-            let inner_struct_ident = format_ident!("{}", inner_struct_name);
-            let inner_type_const = struct_type_const(&inner_struct_name);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        if ring_api_isstring(p, 2) {
-                            let __s = ring_get_string!(p, 2);
-                            if __s.is_empty() {
-                                obj.#field_name = None;
-                            } else {
-                                ring_error!(p, "Expected cpointer or empty string for Option<Struct>");
-                            }
-                        } else if ring_api_ispointer(p, 2) {
-                            let __ptr = ring_get_cpointer!(p, 2, #inner_type_const);
-                            if __ptr.is_null() {
-                                obj.#field_name = None;
-                            } else {
-                                let __val = unsafe { &*(__ptr as *const #inner_struct_ident) };
-                                obj.#field_name = Some(__val.clone());
-                            }
-                        } else {
-                            ring_error!(p, "Expected cpointer or empty string for Option<Struct>");
-                        }
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        } else {
-            let cast = get_number_cast(&inner);
-            quote! {
-                ring_func!(#fn_name, |p| {
-                    ring_check_paracount!(p, 2);
-                    ring_check_cpointer!(p, 1);
-                    if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                        if ring_api_isstring(p, 2) {
-                            let __s = ring_get_string!(p, 2);
-                            if __s.is_empty() {
-                                obj.#field_name = None;
-                            } else {
-                                ring_error!(p, "Expected number or empty string for Option");
-                            }
-                        } else if ring_api_isnumber(p, 2) {
-                            obj.#field_name = Some(ring_get_number!(p, 2) as #cast);
-                        } else {
-                            ring_error!(p, "Expected number or empty string for Option");
-                        }
-                    } else {
-                        ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                    }
-                });
-            }
-        }
-    } else if is_struct_type(&type_str) {
-        let field_struct_name = extract_struct_name(&type_str);
-        let field_struct_ident = format_ident!("{}", field_struct_name);
-        let field_type_const = struct_type_const(&field_struct_name);
-        quote! {
-            ring_func!(#fn_name, |p| {
-                ring_check_paracount!(p, 2);
-                ring_check_cpointer!(p, 1);
-                ring_check_cpointer!(p, 2);
-                if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                    if let Some(val) = ring_get_pointer!(p, 2, #field_struct_ident, #field_type_const) {
-                        obj.#field_name = val.clone();
-                    } else {
-                        ring_error!(p, concat!("Invalid ", #field_struct_name, " pointer"));
-                    }
-                } else {
-                    ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                }
-            });
-        }
+fn generate_field_setter(
+    fn_name: &syn::Ident,
+    subst: &TypeSubst,
+    field_name: &syn::Ident,
+    field_type: &Type,
+    passing: Passing,
+    names: &RingNames,
+) -> TokenStream2 {
+    let self_type = subst.self_type();
+    let field_type = ring_type(field_type, subst);
+    let get = if passing == Passing::Serde {
+        serde_get(&field_type, 2)
+    } else if names.is_by_value_struct(&field_type) {
+        by_value_get(&field_type, 2)
     } else {
-        quote! {
-            ring_func!(#fn_name, |p| {
-                ring_check_paracount!(p, 2);
-                ring_check_cpointer!(p, 1);
-                ring_check_number!(p, 2);
-                if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                    obj.#field_name = ring_get_number!(p, 2) as _;
-                } else {
-                    ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-                }
-            });
-        }
+        byte_buffer_get(&field_type, 2, passing)
+            .unwrap_or_else(|| quote_spanned! {field_type.span()=> ring_get!(p, 2, #field_type) })
+    };
+    quote! {
+        ring_func!(#fn_name, |p| {
+            ring_check_paracount!(p, 2);
+            // Read first: the new value may be a view into `obj`.
+            let value = #get;
+            let obj = ring_get!(p, 1, &mut #self_type);
+            obj.#field_name = value;
+        });
     }
 }
 
 fn generate_custom_new(
-    struct_name: &syn::Ident,
-    struct_name_lower: &str,
-    type_const: &syn::Ident,
+    subst: &TypeSubst,
     method: &ImplItemFn,
-    prefix: &str,
+    ring_name: String,
+    names: &RingNames,
 ) -> (TokenStream2, String, syn::Ident) {
-    let fn_name = format_ident!("ring_{}{}_new", prefix, struct_name_lower);
-    let ring_name = format!("{}{}_new", prefix, struct_name_lower);
+    let fn_name = format_ident!("ring_{}", ring_name);
+    let self_type = subst.self_type();
 
-    let params: Vec<_> = method
-        .sig
-        .inputs
-        .iter()
-        .filter_map(|arg| {
-            if let FnArg::Typed(pat) = arg {
-                let name = if let Pat::Ident(ident) = &*pat.pat {
-                    ident.ident.clone()
-                } else {
-                    return None;
-                };
-                Some((name, (*pat.ty).clone()))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    let param_count = params.len() as i32;
-    let mut checks = Vec::new();
-    let mut gets = Vec::new();
-    let mut args = Vec::new();
+    let ParamBindings {
+        gets,
+        args,
+        checks,
+        count_check,
+    } = generate_param_bindings(&method.sig.inputs, 1, subst, names);
 
-    for (i, (name, ty)) in params.iter().enumerate() {
-        let idx = (i + 1) as i32;
-        let binding = generate_param_binding(name, ty, idx);
-        checks.push(binding.check);
-        gets.push(binding.get);
-        args.push(binding.arg);
-    }
+    let return_code = generate_return_code(
+        &method.sig.output,
+        checked_call(quote! { <#self_type>::new(#(#args),*) }, &checks),
+        subst,
+        ring_attrs(&method.attrs).passing(),
+    );
 
     let code = quote! {
         ring_func!(#fn_name, |p| {
-            ring_check_paracount!(p, #param_count);
-            #(#checks)*
+            #count_check
             #(#gets)*
-            let obj = Box::new(#struct_name::new(#(#args),*));
-            ring_ret_cpointer!(p, Box::into_raw(obj), #type_const);
+            #return_code
         });
     };
 
@@ -943,1222 +2174,781 @@ fn generate_custom_new(
 }
 
 fn generate_method(
-    struct_name: &syn::Ident,
-    struct_name_lower: &str,
-    type_const: &syn::Ident,
+    subst: &TypeSubst,
     method: &ImplItemFn,
-    prefix: &str,
+    ring_name: String,
+    names: &RingNames,
 ) -> (TokenStream2, String, syn::Ident) {
-    let method_name = &method.sig.ident;
-    let fn_name = format_ident!("ring_{}{}_{}", prefix, struct_name_lower, method_name);
-    let ring_name = format!("{}{}_{}", prefix, struct_name_lower, method_name);
-
-    let params: Vec<_> = method
-        .sig
-        .inputs
-        .iter()
-        .filter_map(|arg| {
-            if let FnArg::Typed(pat) = arg {
-                let name = if let Pat::Ident(ident) = &*pat.pat {
-                    ident.ident.clone()
-                } else {
-                    return None;
-                };
-                Some((name, (*pat.ty).clone()))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    let param_count = (params.len() + 1) as i32;
-    let mut checks = Vec::new();
-    let mut gets = Vec::new();
-    let mut args = Vec::new();
-
-    for (i, (name, ty)) in params.iter().enumerate() {
-        let idx = (i + 2) as i32;
-        let binding = generate_param_binding(name, ty, idx);
-        checks.push(binding.check);
-        gets.push(binding.get);
-        args.push(binding.arg);
-    }
-
-    let return_code = generate_return_code_with_context(
-        &method.sig.output,
-        quote! { obj.#method_name(#(#args),*) },
-        Some(struct_name),
-    );
-
-    let code = quote! {
-        ring_func!(#fn_name, |p| {
-            ring_check_paracount!(p, #param_count);
-            ring_check_cpointer!(p, 1);
-            #(#checks)*
-            if let Some(obj) = ring_get_pointer!(p, 1, #struct_name, #type_const) {
-                #(#gets)*
-                #return_code
-            } else {
-                ring_error!(p, concat!("Invalid ", stringify!(#struct_name), " pointer"));
-            }
-        });
+    let self_type = subst.self_type();
+    // `&self` and `&mut self` borrow the boxed value, `self` works on a clone.
+    let obj = match method.sig.receiver().map(|r| &*r.ty) {
+        Some(Type::Reference(r)) if r.mutability.is_some() => {
+            let checks = overlap_checks(&method.sig, true, subst, names);
+            quote! {{
+                #(#checks)*
+                ring_get!(p, 1, &mut #self_type)
+            }}
+        }
+        Some(Type::Reference(_)) => {
+            let checks = overlap_checks(&method.sig, false, subst, names);
+            quote! {{
+                #(#checks)*
+                ring_get!(p, 1, &#self_type)
+            }}
+        }
+        _ => by_value_get(self_type, 1),
     };
-
-    (code, ring_name, fn_name)
+    let passing = ring_attrs(&method.attrs).passing();
+    generate_receiver_method(&method.sig, obj, subst, passing, ring_name, names)
 }
 
-fn generate_static_method(
-    struct_name: &syn::Ident,
-    struct_name_lower: &str,
-    _type_const: &syn::Ident,
-    method: &ImplItemFn,
-    prefix: &str,
-) -> (TokenStream2, String, syn::Ident) {
-    let method_name = &method.sig.ident;
-    let fn_name = format_ident!("ring_{}{}_{}", prefix, struct_name_lower, method_name);
-    let ring_name = format!("{}{}_{}", prefix, struct_name_lower, method_name);
-
-    let params: Vec<_> = method
-        .sig
-        .inputs
+/// Checks that `&`/`&mut` struct parameters don't point into the receiver (a
+/// `#[ring(view)]` of it, or the receiver itself) when either borrow is
+/// `&mut`, raising a Ring error instead of creating aliasing references.
+/// Structs passed by value are cloned before the receiver is borrowed, so
+/// they are not checked.
+fn overlap_checks(
+    sig: &syn::Signature,
+    receiver_mut: bool,
+    subst: &TypeSubst,
+    names: &RingNames,
+) -> Vec<TokenStream2> {
+    let self_type = subst.self_type();
+    sig.inputs
         .iter()
-        .filter_map(|arg| {
-            if let FnArg::Typed(pat) = arg {
-                let name = if let Pat::Ident(ident) = &*pat.pat {
-                    ident.ident.clone()
-                } else {
-                    return None;
-                };
-                Some((name, (*pat.ty).clone()))
-            } else {
-                None
-            }
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => Some(ring_type(&pat.ty, subst)),
+            FnArg::Receiver(_) => None,
         })
-        .collect();
-
-    let param_count = params.len() as i32;
-    let mut checks = Vec::new();
-    let mut gets = Vec::new();
-    let mut args = Vec::new();
-
-    for (i, (name, ty)) in params.iter().enumerate() {
-        let idx = (i + 1) as i32;
-        let binding = generate_param_binding(name, ty, idx);
-        checks.push(binding.check);
-        gets.push(binding.get);
-        args.push(binding.arg);
-    }
+        .zip(2..)
+        .filter_map(|(ty, idx): (Type, i32)| {
+            let (target, param_mut) = match &ty {
+                Type::Reference(r) if names.is_by_value_struct(&r.elem) => {
+                    ((*r.elem).clone(), r.mutability.is_some())
+                }
+                _ => return None,
+            };
+            (receiver_mut || param_mut).then(|| {
+                let msg = format!("parameter {idx} points into the object the method is called on");
+                quote! {
+                    if ring_pointer_params_overlap::<#self_type, #target>(&p, 1, #idx) {
+                        ring_error!(p, #msg);
+                        return;
+                    }
+                }
+            })
+        })
+        .collect()
+}
 
-    let return_code = generate_return_code_with_context(
-        &method.sig.output,
-        quote! { #struct_name::#method_name(#(#args),*) },
-        Some(struct_name),
+/// A wrapper reading the receiver from parameter 1 with `obj`, after the other
+/// parameters so by-value structs are cloned before it is borrowed.
+fn generate_receiver_method(
+    sig: &syn::Signature,
+    obj: TokenStream2,
+    subst: &TypeSubst,
+    passing: Passing,
+    ring_name: String,
+    names: &RingNames,
+) -> (TokenStream2, String, syn::Ident) {
+    let method_name = &sig.ident;
+    let fn_name = format_ident!("ring_{}", ring_name);
+
+    let ParamBindings {
+        gets,
+        args,
+        checks,
+        count_check,
+    } = generate_param_bindings(&sig.inputs, 2, subst, names);
+
+    let return_code = generate_return_code(
+        &sig.output,
+        checked_call(quote! { obj.#method_name(#(#args),*) }, &checks),
+        subst,
+        passing,
     );
 
     let code = quote! {
         ring_func!(#fn_name, |p| {
-            ring_check_paracount!(p, #param_count);
-            #(#checks)*
+            #count_check
             #(#gets)*
+            let obj = #obj;
             #return_code
-        });
-    };
-
-    (code, ring_name, fn_name)
-}
-
-fn generate_return_code(output: &ReturnType, call: TokenStream2) -> TokenStream2 {
-    generate_return_code_with_context(output, call, None)
-}
-
-fn generate_return_code_with_context(
-    output: &ReturnType,
-    call: TokenStream2,
-    self_struct: Option<&syn::Ident>,
-) -> TokenStream2 {
-    match output {
-        ReturnType::Default => quote! { #call; },
-        ReturnType::Type(_, ty) => {
-            let type_str = quote!(#ty).to_string();
-            generate_return_for_type(&type_str, call, self_struct)
-        }
-    }
-}
-
-fn generate_return_for_type(
-    type_str: &str,
-    call: TokenStream2,
-    self_struct: Option<&syn::Ident>,
-) -> TokenStream2 {
-    let type_str = type_str.trim();
-
-    if is_number_type(type_str) {
-        quote! {
-            let __result = #call;
-            ring_ret_number!(p, __result as f64);
-        }
-    } else if is_string_type(type_str) {
-        quote! {
-            let __result = #call;
-            ring_ret_string!(p, &__result);
-        }
-    } else if type_str == "bool" {
-        quote! {
-            let __result = #call;
-            ring_ret_number!(p, if __result { 1.0 } else { 0.0 });
-        }
-    } else if is_vec_type(type_str) {
-        generate_vec_return(type_str, call)
-    } else if is_option_type(type_str) {
-        generate_option_return(type_str, call)
-    } else if is_result_type(type_str) {
-        generate_result_return(type_str, call)
-    } else if is_tuple_type(type_str) {
-        generate_tuple_return(type_str, call)
-    } else if is_box_type(type_str) {
-        generate_box_return(type_str, call)
-    } else if is_hashmap_type(type_str) {
-        generate_hashmap_return(type_str, call)
-    } else if type_str == "Self" {
-        if let Some(struct_name) = self_struct {
-            let type_const = struct_type_const(&struct_name.to_string());
-            quote! {
-                let __result = #call;
-                ring_ret_cpointer!(p, Box::into_raw(Box::new(__result)), #type_const);
-            }
-        } else {
-            quote! {
-                let __result = #call;
-                ring_ret_number!(p, __result as f64);
-            }
-        }
-    } else if is_struct_type(type_str) {
-        let struct_name = extract_struct_name(type_str);
-        let type_const = struct_type_const(&struct_name);
-        quote! {
-            let __result = #call;
-            ring_ret_cpointer!(p, Box::into_raw(Box::new(__result)), #type_const);
-        }
-    } else {
-        quote! {
-            let __result = #call;
-            ring_ret_number!(p, __result as f64);
-        }
-    }
-}
-
-fn generate_vec_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let inner = extract_vec_inner(type_str).unwrap_or_default();
-
-    if is_number_type(&inner) {
-        quote! {
-            let __result = #call;
-            let __list = ring_new_list!(p);
-            for __item in __result {
-                ring_list_adddouble(__list, __item as f64);
-            }
-            ring_ret_list!(p, __list);
-        }
-    } else if is_string_type(&inner) {
-        quote! {
-            let __result = #call;
-            let __list = ring_new_list!(p);
-            for __item in __result {
-                ring_list_addstring_str(__list, &__item);
-            }
-            ring_ret_list!(p, __list);
-        }
-    } else if is_option_type(&inner) {
-        let opt_inner = extract_option_inner(&inner).unwrap_or_default();
-        if is_number_type(&opt_inner) {
-            quote! {
-                let __result = #call;
-                let __list = ring_new_list!(p);
-                for __item in __result {
-                    match __item {
-                        Some(__val) => ring_list_adddouble(__list, __val as f64),
-                        None => ring_list_newitem(__list),
-                    }
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else if is_string_type(&opt_inner) {
-            quote! {
-                let __result = #call;
-                let __list = ring_new_list!(p);
-                for __item in __result {
-                    match __item {
-                        Some(__val) => ring_list_addstring_str(__list, &__val),
-                        None => ring_list_newitem(__list),
-                    }
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else if is_struct_type(&opt_inner) {
-            let type_const = struct_type_const(&extract_struct_name(&opt_inner));
-            quote! {
-                let __result = #call;
-                let __list = ring_new_list!(p);
-                for __item in __result {
-                    match __item {
-                        Some(__val) => {
-                            let __ptr = Box::into_raw(Box::new(__val));
-                            ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #type_const);
-                        }
-                        None => ring_list_newitem(__list),
-                    }
-                }
-                ring_ret_list!(p, __list);
-            }
-        } else {
-            quote! {
-                let __result = #call;
-                let __list = ring_new_list!(p);
-                for __item in __result {
-                    match __item {
-                        Some(__val) => ring_list_adddouble(__list, __val as f64),
-                        None => ring_list_newitem(__list),
-                    }
-                }
-                ring_ret_list!(p, __list);
-            }
-        }
-    } else if is_vec_type(&inner) {
-        let inner_inner = extract_vec_inner(&inner).unwrap_or_default();
-        let add_inner_item = if is_number_type(&inner_inner) {
-            quote! { ring_list_adddouble(__inner_list, __inner_item as f64); }
-        } else if is_string_type(&inner_inner) {
-            quote! { ring_list_addstring_str(__inner_list, &__inner_item); }
-        } else if is_struct_type(&inner_inner) {
-            let type_const = struct_type_const(&extract_struct_name(&inner_inner));
-            quote! {
-                let __ptr = Box::into_raw(Box::new(__inner_item));
-                ring_list_addcpointer(__inner_list, __ptr as *mut std::ffi::c_void, #type_const);
-            }
-        } else {
-            quote! { ring_list_adddouble(__inner_list, __inner_item as f64); }
-        };
-
-        quote! {
-            let __result = #call;
-            let __list = ring_new_list!(p);
-            for __item in __result {
-                let __inner_list = ring_list_newlist(__list);
-                for __inner_item in __item {
-                    #add_inner_item
-                }
-            }
-            ring_ret_list!(p, __list);
-        }
-    } else if is_struct_type(&inner) {
-        let type_const = struct_type_const(&extract_struct_name(&inner));
-        quote! {
-            let __result = #call;
-            let __list = ring_new_list!(p);
-            for __item in __result {
-                let __ptr = Box::into_raw(Box::new(__item));
-                ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #type_const);
-            }
-            ring_ret_list!(p, __list);
-        }
-    } else {
-        quote! {
-            let __result = #call;
-            let __list = ring_new_list!(p);
-            for __item in __result {
-                ring_list_adddouble(__list, __item as f64);
-            }
-            ring_ret_list!(p, __list);
-        }
-    }
-}
-
-fn generate_option_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let inner = extract_option_inner(type_str).unwrap_or_default();
-
-    if is_number_type(&inner) {
-        quote! {
-            let __result = #call;
-            match __result {
-                Some(__val) => ring_ret_number!(p, __val as f64),
-                None => {},
-            }
-        }
-    } else if is_string_type(&inner) {
-        quote! {
-            let __result = #call;
-            match __result {
-                Some(__val) => ring_ret_string!(p, &__val),
-                None => {},
-            }
-        }
-    } else if is_vec_type(&inner) {
-        let vec_inner = extract_vec_inner(&inner).unwrap_or_default();
-        if is_number_type(&vec_inner) {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Some(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_adddouble(__list, __item as f64);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    None => {},
-                }
-            }
-        } else if is_string_type(&vec_inner) {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Some(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_addstring_str(__list, &__item);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    None => {},
-                }
-            }
-        } else if is_struct_type(&vec_inner) {
-            let type_const = struct_type_const(&extract_struct_name(&vec_inner));
-            quote! {
-                let __result = #call;
-                match __result {
-                    Some(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            let __ptr = Box::into_raw(Box::new(__item));
-                            ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #type_const);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    None => {},
-                }
-            }
-        } else {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Some(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_adddouble(__list, __item as f64);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    None => {},
-                }
-            }
-        }
-    } else if is_struct_type(&inner) {
-        let type_const = struct_type_const(&extract_struct_name(&inner));
-        quote! {
-            let __result = #call;
-            match __result {
-                Some(__val) => {
-                    ring_ret_cpointer!(p, Box::into_raw(Box::new(__val)), #type_const);
-                }
-                None => {},
-            }
-        }
-    } else {
-        quote! {
-            let __result = #call;
-            match __result {
-                Some(__val) => ring_ret_number!(p, __val as f64),
-                None => {},
-            }
-        }
-    }
-}
-
-fn generate_result_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let ok_type = extract_result_ok(type_str).unwrap_or_default();
-
-    if ok_type == "()" || ok_type.is_empty() {
-        quote! {
-            let __result = #call;
-            match __result {
-                Ok(_) => {}
-                Err(__e) => ring_error!(p, &format!("{}", __e)),
-            }
-        }
-    } else if is_number_type(&ok_type) {
-        quote! {
-            let __result = #call;
-            match __result {
-                Ok(__val) => ring_ret_number!(p, __val as f64),
-                Err(__e) => ring_error!(p, &format!("{}", __e)),
-            }
-        }
-    } else if is_string_type(&ok_type) {
-        quote! {
-            let __result = #call;
-            match __result {
-                Ok(__val) => ring_ret_string!(p, &__val),
-                Err(__e) => ring_error!(p, &format!("{}", __e)),
-            }
-        }
-    } else if is_vec_type(&ok_type) {
-        let vec_inner = extract_vec_inner(&ok_type).unwrap_or_default();
-        if is_number_type(&vec_inner) {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Ok(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_adddouble(__list, __item as f64);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    Err(__e) => ring_error!(p, &format!("{}", __e)),
-                }
-            }
-        } else if is_string_type(&vec_inner) {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Ok(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_addstring_str(__list, &__item);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    Err(__e) => ring_error!(p, &format!("{}", __e)),
-                }
-            }
-        } else if is_struct_type(&vec_inner) {
-            let type_const = struct_type_const(&extract_struct_name(&vec_inner));
-            quote! {
-                let __result = #call;
-                match __result {
-                    Ok(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            let __ptr = Box::into_raw(Box::new(__item));
-                            ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #type_const);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    Err(__e) => ring_error!(p, &format!("{}", __e)),
-                }
-            }
-        } else {
-            quote! {
-                let __result = #call;
-                match __result {
-                    Ok(__vec) => {
-                        let __list = ring_new_list!(p);
-                        for __item in __vec {
-                            ring_list_adddouble(__list, __item as f64);
-                        }
-                        ring_ret_list!(p, __list);
-                    }
-                    Err(__e) => ring_error!(p, &format!("{}", __e)),
-                }
-            }
-        }
-    } else if is_struct_type(&ok_type) {
-        let type_const = struct_type_const(&extract_struct_name(&ok_type));
-        quote! {
-            let __result = #call;
-            match __result {
-                Ok(__val) => {
-                    ring_ret_cpointer!(p, Box::into_raw(Box::new(__val)), #type_const);
-                }
-                Err(__e) => ring_error!(p, &format!("{}", __e)),
-            }
-        }
-    } else {
-        quote! {
-            let __result = #call;
-            match __result {
-                Ok(__val) => ring_ret_number!(p, __val as f64),
-                Err(__e) => ring_error!(p, &format!("{}", __e)),
-            }
-        }
-    }
-}
-
-fn generate_tuple_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let elements = extract_tuple_elements(type_str);
+        });
+    };
 
-    if elements.is_empty() {
-        return quote! { #call; };
-    }
+    (code, ring_name, fn_name)
+}
 
-    let mut add_statements = Vec::new();
+fn generate_static_method(
+    subst: &TypeSubst,
+    method: &ImplItemFn,
+    ring_name: String,
+    names: &RingNames,
+) -> (TokenStream2, String, syn::Ident) {
+    let method_name = &method.sig.ident;
+    let fn_name = format_ident!("ring_{}", ring_name);
+    let self_type = subst.self_type();
 
-    for (i, elem_type) in elements.iter().enumerate() {
-        let idx = syn::Index::from(i);
+    let ParamBindings {
+        gets,
+        args,
+        checks,
+        count_check,
+    } = generate_param_bindings(&method.sig.inputs, 1, subst, names);
 
-        let add_stmt = if is_number_type(elem_type) {
-            quote! { ring_list_adddouble(__list, __result.#idx as f64); }
-        } else if is_string_type(elem_type) {
-            quote! { ring_list_addstring_str(__list, &__result.#idx); }
-        } else if elem_type == "bool" {
-            quote! { ring_list_adddouble(__list, if __result.#idx { 1.0 } else { 0.0 }); }
-        } else if is_struct_type(elem_type) {
-            let type_const = struct_type_const(&extract_struct_name(elem_type));
-            quote! {
-                let __ptr = Box::into_raw(Box::new(__result.#idx));
-                ring_list_addcpointer(__list, __ptr as *mut std::ffi::c_void, #type_const);
-            }
-        } else {
-            quote! { ring_list_adddouble(__list, __result.#idx as f64); }
-        };
+    let return_code = generate_return_code(
+        &method.sig.output,
+        checked_call(quote! { <#self_type>::#method_name(#(#args),*) }, &checks),
+        subst,
+        ring_attrs(&method.attrs).passing(),
+    );
 
-        add_statements.push(add_stmt);
-    }
+    let code = quote! {
+        ring_func!(#fn_name, |p| {
+            #count_check
+            #(#gets)*
+            #return_code
+        });
+    };
 
-    quote! {
-        let __result = #call;
-        let __list = ring_new_list!(p);
-        #(#add_statements)*
-        ring_ret_list!(p, __list);
-    }
+    (code, ring_name, fn_name)
 }
 
-fn generate_box_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let inner = extract_box_inner(type_str).unwrap_or_default();
-
-    if is_number_type(&inner) {
-        quote! {
-            let __result = #call;
-            ring_ret_number!(p, *__result as f64);
-        }
-    } else if is_string_type(&inner) {
-        quote! {
-            let __result = #call;
-            ring_ret_string!(p, &*__result);
-        }
-    } else if inner == "bool" {
-        quote! {
-            let __result = #call;
-            ring_ret_number!(p, if *__result { 1.0 } else { 0.0 });
-        }
-    } else if is_struct_type(&inner) {
-        let type_const = struct_type_const(&extract_struct_name(&inner));
+/// Return the call's value with `ring_ret!`. A `Result` returns its `Ok`
+/// value and raises its `Err` as a Ring error. Byte buffers return as
+/// strings, or as lists with `#[ring(list)]`; `#[ring(serde)]` values are
+/// wrapped in `RingSerde`.
+///
+/// The macro calls carry the span of the return type, so a type without an
+/// `IntoRing` impl is reported there.
+fn generate_return_code(
+    output: &ReturnType,
+    call: TokenStream2,
+    subst: &TypeSubst,
+    passing: Passing,
+) -> TokenStream2 {
+    let ty = match output {
+        ReturnType::Default => return quote! { #call; },
+        ReturnType::Type(_, ty) => ring_type(ty, subst),
+    };
+    let ty = &ty;
+    if let Some(ok_type) = result_ok_type(ty) {
+        let value = ret_value(ok_type, quote! { __value }, passing);
+        let ret = quote_spanned! {ok_type.span()=> ring_ret!(p, #value) };
         quote! {
-            let __result = #call;
-            ring_ret_cpointer!(p, Box::into_raw(__result), #type_const);
+            match #call {
+                Ok(__value) => #ret,
+                Err(__err) => ring_error!(p, &__err.to_string()),
+            }
         }
     } else {
+        let value = ret_value(ty, quote! { __result }, passing);
+        let ret = quote_spanned! {ty.span()=> ring_ret!(p, #value) };
         quote! {
             let __result = #call;
-            ring_ret_number!(p, *__result as f64);
+            #ret;
         }
     }
 }
 
-fn generate_hashmap_return(type_str: &str, call: TokenStream2) -> TokenStream2 {
-    let (key_type, value_type) = extract_hashmap_kv(type_str).unwrap_or_default();
+/// What `ring_ret!` returns for `value` of type `ty`.
+fn ret_value(ty: &Type, value: TokenStream2, passing: Passing) -> TokenStream2 {
+    if passing == Passing::Serde {
+        return quote! { RingSerde(#value) };
+    }
+    byte_buffer_ret(ty, value.clone(), passing).unwrap_or(value)
+}
 
-    let add_key = if is_number_type(&key_type) {
-        quote! { ring_list_adddouble(__pair, __k as f64); }
-    } else if is_string_type(&key_type) {
-        quote! { ring_list_addstring_str(__pair, &__k); }
-    } else {
-        quote! { ring_list_addstring_str(__pair, &format!("{:?}", __k)); }
-    };
+/// Bindings for all typed parameters of a wrapped fn, numbered from `first`.
+struct ParamBindings {
+    gets: Vec<TokenStream2>,
+    args: Vec<TokenStream2>,
+    checks: Vec<TokenStream2>,
+    /// `ring_check_paracount!`, or `ring_check_paracount_range!` when
+    /// trailing parameters are optional.
+    count_check: TokenStream2,
+}
 
-    let add_value = if is_number_type(&value_type) {
-        quote! { ring_list_adddouble(__pair, __v as f64); }
-    } else if is_string_type(&value_type) {
-        quote! { ring_list_addstring_str(__pair, &__v); }
-    } else if value_type == "bool" {
-        quote! { ring_list_adddouble(__pair, if __v { 1.0 } else { 0.0 }); }
-    } else if is_struct_type(&value_type) {
-        let type_const = struct_type_const(&extract_struct_name(&value_type));
-        quote! {
-            let __ptr = Box::into_raw(Box::new(__v));
-            ring_list_addcpointer(__pair, __ptr as *mut std::ffi::c_void, #type_const);
-        }
-    } else {
-        quote! { ring_list_adddouble(__pair, __v as f64); }
-    };
+/// Trailing `Option<T>` and `#[ring(default = ...)]` parameters may be left
+/// out by the Ring caller; they become `None` or the default.
+fn generate_param_bindings(
+    inputs: &syn::punctuated::Punctuated<FnArg, Token![,]>,
+    first: i32,
+    subst: &TypeSubst,
+    names: &RingNames,
+) -> ParamBindings {
+    let params: Vec<_> = inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => match &*pat.pat {
+                Pat::Ident(ident) => {
+                    let attrs = ring_attrs(&pat.attrs);
+                    let passing = attrs.passing();
+                    Some((
+                        ident.ident.clone(),
+                        ring_type(&pat.ty, subst),
+                        attrs.default,
+                        passing,
+                    ))
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
 
-    quote! {
-        let __result = #call;
-        let __list = ring_new_list!(p);
-        for (__k, __v) in __result {
-            let __pair = ring_list_newlist(__list);
-            #add_key
-            #add_value
-        }
-        ring_ret_list!(p, __list);
-    }
-}
-
-fn is_number_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    matches!(
-        ty,
-        "i8" | "i16"
-            | "i32"
-            | "i64"
-            | "i128"
-            | "isize"
-            | "u8"
-            | "u16"
-            | "u32"
-            | "u64"
-            | "u128"
-            | "usize"
-            | "f32"
-            | "f64"
-    )
-}
+    let required = params
+        .iter()
+        .rposition(|(_, ty, default, _)| default.is_none() && !is_option(ty))
+        .map_or(0, |i| i + 1);
 
-fn is_string_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty == "String" || ty == "& str" || ty.contains("str")
-}
+    let mut gets = Vec::new();
+    let mut args = Vec::new();
+    let mut checks = Vec::new();
 
-fn get_number_cast(ty: &str) -> TokenStream2 {
-    let ty = ty.trim();
-    match ty {
-        "i8" => quote!(i8),
-        "i16" => quote!(i16),
-        "i32" => quote!(i32),
-        "i64" => quote!(i64),
-        "i128" => quote!(i128),
-        "isize" => quote!(isize),
-        "u8" => quote!(u8),
-        "u16" => quote!(u16),
-        "u32" => quote!(u32),
-        "u64" => quote!(u64),
-        "u128" => quote!(u128),
-        "usize" => quote!(usize),
-        "f32" => quote!(f32),
-        "f64" => quote!(f64),
-        _ => quote!(f64),
+    for (i, (name, ty, default, passing)) in params.iter().enumerate() {
+        let idx = first + i as i32;
+        let binding = generate_param_binding(name, ty, idx, *passing, names);
+        checks.extend(binding.check);
+        if i < required {
+            gets.push(binding.get);
+            args.push(binding.arg);
+            continue;
+        }
+        let default = default.clone().unwrap_or_else(|| syn::parse_quote!(None));
+        let (get, arg) = (binding.get, binding.arg);
+        gets.push(quote! {
+            let #name = if ring_api_paracount(p) >= #idx {
+                #get
+                #arg
+            } else {
+                #default
+            };
+        });
+        args.push(quote! { #name });
     }
-}
 
-fn is_vec_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("Vec <") || ty.starts_with("Vec<")
-}
+    let max = first - 1 + params.len() as i32;
+    let min = first - 1 + required as i32;
+    let count_check = if min == max {
+        quote! { ring_check_paracount!(p, #max); }
+    } else {
+        quote! { ring_check_paracount_range!(p, #min, #max); }
+    };
 
-fn is_option_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("Option <") || ty.starts_with("Option<")
+    ParamBindings {
+        gets,
+        args,
+        checks,
+        count_check,
+    }
 }
 
-fn is_result_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("Result <") || ty.starts_with("Result<")
+/// Wrap a call to run the parameters' checks after it and before its value is
+/// returned.
+fn checked_call(call: TokenStream2, checks: &[TokenStream2]) -> TokenStream2 {
+    if checks.is_empty() {
+        return call;
+    }
+    quote! {{
+        let __ret = #call;
+        #(#checks)*
+        __ret
+    }}
 }
 
-fn is_tuple_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with('(') && ty.ends_with(')') && ty.contains(',')
+struct ParamBinding {
+    get: TokenStream2,
+    arg: TokenStream2,
+    /// Run after the call, e.g. to raise the error of a failed callback.
+    check: Option<TokenStream2>,
 }
 
-fn is_box_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("Box <") || ty.starts_with("Box<")
+/// Read parameter `idx` with `ring_get!`, spanned at its type so a type
+/// without a `FromRing` impl is reported there. Slices are read into a `Vec`
+/// and passed borrowed, byte buffers are read from strings (unless
+/// `#[ring(list)]`), `#[ring(serde)]` values through `RingSerde`, structs
+/// passed by value are cloned and callbacks become closures over a
+/// `RingCallback`.
+fn generate_param_binding(
+    name: &syn::Ident,
+    ty: &Type,
+    idx: i32,
+    passing: Passing,
+    names: &RingNames,
+) -> ParamBinding {
+    if passing == Passing::Serde {
+        let get = serde_get(ty, idx);
+        return ParamBinding {
+            get: quote! { let #name = #get; },
+            arg: quote! { #name },
+            check: None,
+        };
+    }
+    if let Some((sig, kind)) = callback_signature(ty) {
+        return generate_callback_binding(name, sig, kind, idx);
+    }
+    if names.is_by_value_struct(ty) {
+        let get = by_value_get(ty, idx);
+        return ParamBinding {
+            get: quote! { let #name = #get; },
+            arg: quote! { #name },
+            check: None,
+        };
+    }
+    if let Some(get) = byte_buffer_get(ty, idx, passing) {
+        return ParamBinding {
+            get: quote! { let #name = #get; },
+            arg: quote! { #name },
+            check: None,
+        };
+    }
+    if let Type::Reference(r) = ty {
+        if let Type::Slice(slice) = &*r.elem {
+            if passing != Passing::List && byte_buffer(ty) == Some(ByteBuffer::Slice) {
+                let get = quote_spanned! {ty.span()=> ring_get!(p, #idx, &[u8]) };
+                return ParamBinding {
+                    get: quote! { let #name = #get; },
+                    arg: quote! { #name },
+                    check: None,
+                };
+            }
+            let inner = &slice.elem;
+            let vec_name = format_ident!("__{}_vec", name);
+            let get = quote_spanned! {inner.span()=> ring_get!(p, #idx, Vec<#inner>) };
+            return if r.mutability.is_some() {
+                ParamBinding {
+                    get: quote! { let mut #vec_name = #get; },
+                    arg: quote! { &mut #vec_name[..] },
+                    check: None,
+                }
+            } else {
+                ParamBinding {
+                    get: quote! { let #vec_name = #get; },
+                    arg: quote! { &#vec_name[..] },
+                    check: None,
+                }
+            };
+        }
+    }
+    let get = quote_spanned! {ty.span()=> ring_get!(p, #idx, #ty) };
+    ParamBinding {
+        get: quote! { let #name = #get; },
+        arg: quote! { #name },
+        check: None,
+    }
 }
 
-fn is_hashmap_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("HashMap <")
-        || ty.starts_with("HashMap<")
-        || ty.starts_with("std :: collections :: HashMap")
-        || ty.contains("HashMap <")
-        || ty.contains("HashMap<")
+/// Read parameter `idx` through serde, reporting a missing `Deserialize` at
+/// `ty`.
+fn serde_get(ty: &Type, idx: i32) -> TokenStream2 {
+    quote_spanned! {ty.span()=> ring_get!(p, #idx, RingSerde<#ty>).0 }
 }
 
-fn is_slice_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("& [") || ty.starts_with("&[")
+/// Clone the boxed struct of parameter `idx`, reporting a missing `Clone` at
+/// `ty`.
+fn by_value_get(ty: &Type, idx: i32) -> TokenStream2 {
+    quote_spanned! {ty.span()=>
+        <#ty as RingByValue>::ring_by_value(ring_get!(p, #idx, &#ty))
+    }
 }
 
-fn is_struct_type(ty: &str) -> bool {
-    let ty = ty.trim();
-    let ty = ty.trim_start_matches('&').trim_start_matches("mut ").trim();
+/// How a callback parameter is declared.
+#[derive(Clone, Copy)]
+enum CallbackKind {
+    /// `impl Fn(..)`
+    Impl,
+    /// `&dyn Fn(..)`
+    Ref,
+    /// `&mut dyn FnMut(..)`
+    RefMut,
+    /// `Box<dyn Fn(..)>`
+    Boxed,
+}
 
-    if is_vec_type(ty)
-        || is_option_type(ty)
-        || is_result_type(ty)
-        || is_tuple_type(ty)
-        || is_box_type(ty)
-        || is_hashmap_type(ty)
-    {
-        return false;
+/// The `(inputs) -> output` of an `Fn`, `FnMut` or `FnOnce` parameter type.
+fn callback_signature(ty: &Type) -> Option<(&syn::ParenthesizedGenericArguments, CallbackKind)> {
+    fn fn_bound(
+        bounds: &syn::punctuated::Punctuated<syn::TypeParamBound, Token![+]>,
+    ) -> Option<&syn::ParenthesizedGenericArguments> {
+        bounds.iter().find_map(|bound| {
+            let syn::TypeParamBound::Trait(t) = bound else {
+                return None;
+            };
+            let segment = t.path.segments.last()?;
+            let syn::PathArguments::Parenthesized(args) = &segment.arguments else {
+                return None;
+            };
+            matches!(
+                segment.ident.to_string().as_str(),
+                "Fn" | "FnMut" | "FnOnce"
+            )
+            .then_some(args)
+        })
     }
 
-    if is_number_type(ty) || is_string_type(ty) || ty == "bool" || ty == "()" {
-        return false;
+    match ty {
+        Type::ImplTrait(t) => fn_bound(&t.bounds).map(|sig| (sig, CallbackKind::Impl)),
+        Type::Reference(r) => match &*r.elem {
+            Type::TraitObject(t) => {
+                let kind = if r.mutability.is_some() {
+                    CallbackKind::RefMut
+                } else {
+                    CallbackKind::Ref
+                };
+                fn_bound(&t.bounds).map(|sig| (sig, kind))
+            }
+            _ => None,
+        },
+        _ => {
+            let segment = last_segment(ty).filter(|s| s.ident == "Box")?;
+            let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+                return None;
+            };
+            match args.args.first()? {
+                syn::GenericArgument::Type(Type::TraitObject(t)) => {
+                    fn_bound(&t.bounds).map(|sig| (sig, CallbackKind::Boxed))
+                }
+                _ => None,
+            }
+        }
     }
-
-    ty.chars()
-        .next()
-        .map(|c| c.is_ascii_uppercase())
-        .unwrap_or(false)
 }
 
-fn is_struct_ref(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with('&') && is_struct_type(ty)
-}
+/// Read a Ring function name as a `RingCallback` and pass a closure that calls
+/// it. A return type of `Result<T, E>` (with `E: From<RingError>`) gets the
+/// call's error. Otherwise the closure keeps the first error, returns
+/// `Default::default()` from then on without calling Ring again, and the
+/// wrapper raises the error once the Rust function returns.
+fn generate_callback_binding(
+    name: &syn::Ident,
+    sig: &syn::ParenthesizedGenericArguments,
+    kind: CallbackKind,
+    idx: i32,
+) -> ParamBinding {
+    let cb_name = format_ident!("__{}_cb", name);
+    let err_name = format_ident!("__{}_err", name);
+    let arg_names: Vec<_> = (0..sig.inputs.len())
+        .map(|i| format_ident!("__arg{}", i))
+        .collect();
+    let arg_types = sig.inputs.iter();
+    let output = match &sig.output {
+        syn::ReturnType::Default => syn::parse_quote!(()),
+        syn::ReturnType::Type(_, ty) => (**ty).clone(),
+    };
 
-fn extract_vec_inner(ty: &str) -> Option<String> {
-    let ty = ty.trim();
-    if ty.starts_with("Vec <") {
-        Some(ty[5..ty.len() - 1].trim().to_string())
-    } else if ty.starts_with("Vec<") {
-        Some(ty[4..ty.len() - 1].trim().to_string())
+    let call = quote! { #cb_name.call((#(#arg_names,)*)) };
+    let (body, check) = if let Some(ok) = result_ok_type(&output) {
+        (
+            quote_spanned! {ok.span()=> #call.map_err(Into::into) },
+            None,
+        )
     } else {
-        None
-    }
-}
+        let default = quote_spanned! {output.span()=> ::std::default::Default::default() };
+        let body = quote! {
+            if #err_name.get().is_some() {
+                return #default;
+            }
+            #call.unwrap_or_else(|__err: RingError| {
+                let _ = #err_name.set(__err);
+                #default
+            })
+        };
+        let check = quote! {
+            if let Some(__err) = #err_name.get() {
+                ring_error!(p, &__err.message);
+                return;
+            }
+        };
+        (body, Some(check))
+    };
 
-fn extract_option_inner(ty: &str) -> Option<String> {
-    let ty = ty.trim();
-    if ty.starts_with("Option <") {
-        Some(ty[8..ty.len() - 1].trim().to_string())
-    } else if ty.starts_with("Option<") {
-        Some(ty[7..ty.len() - 1].trim().to_string())
-    } else {
-        None
-    }
+    let mutability = matches!(kind, CallbackKind::RefMut).then(|| quote! { mut });
+    let get = quote! {
+        let #cb_name = ring_get!(p, #idx, RingCallback);
+        let #err_name = ::std::rc::Rc::new(::std::cell::OnceCell::<RingError>::new());
+        let #mutability #name = {
+            let #err_name = #err_name.clone();
+            // `Into::into` is a no-op when the error type is `RingError`.
+            #[allow(clippy::useless_conversion)]
+            move |#(#arg_names: #arg_types),*| -> #output { #body }
+        };
+    };
+    let arg = match kind {
+        CallbackKind::Impl => quote! { #name },
+        CallbackKind::Ref => quote! { &#name },
+        CallbackKind::RefMut => quote! { &mut #name },
+        CallbackKind::Boxed => quote! { Box::new(#name) },
+    };
+    ParamBinding { get, arg, check }
 }
 
-fn extract_result_ok(ty: &str) -> Option<String> {
-    let ty = ty.trim();
-    let inner = if ty.starts_with("Result <") {
-        &ty[8..ty.len() - 1]
-    } else if ty.starts_with("Result<") {
-        &ty[7..ty.len() - 1]
-    } else {
-        return None;
-    };
+/// Types a wrapper puts in place of `Self` and of the type parameters of a
+/// generic item.
+#[derive(Clone, Default)]
+struct TypeSubst {
+    self_type: Option<Type>,
+    params: Vec<(Ident, Type)>,
+}
 
-    let mut depth = 0;
-    for (i, c) in inner.char_indices() {
-        match c {
-            '<' => depth += 1,
-            '>' => depth -= 1,
-            ',' if depth == 0 => {
-                return Some(inner[..i].trim().to_string());
-            }
-            _ => {}
+impl TypeSubst {
+    fn for_self(self_type: &Type) -> Self {
+        TypeSubst {
+            self_type: Some(self_type.clone()),
+            params: Vec::new(),
         }
     }
-    None
-}
 
-fn extract_tuple_elements(ty: &str) -> Vec<String> {
-    let ty = ty.trim();
-    if !ty.starts_with('(') || !ty.ends_with(')') {
-        return vec![];
+    /// The `Self` of an impl's methods.
+    fn self_type(&self) -> &Type {
+        self.self_type
+            .as_ref()
+            .expect("methods are generated with a Self type")
     }
 
-    let inner = &ty[1..ty.len() - 1];
-    let mut elements = vec![];
-    let mut depth = 0;
-    let mut start = 0;
-
-    for (i, c) in inner.char_indices() {
-        match c {
-            '<' | '(' => depth += 1,
-            '>' | ')' => depth -= 1,
-            ',' if depth == 0 => {
-                elements.push(inner[start..i].trim().to_string());
-                start = i + 1;
-            }
-            _ => {}
+    fn get(&self, path: &syn::Path) -> Option<&Type> {
+        let ident = path.get_ident()?;
+        if ident == "Self" {
+            return self.self_type.as_ref();
         }
+        self.params
+            .iter()
+            .find(|(p, _)| p == ident)
+            .map(|(_, ty)| ty)
     }
+}
 
-    let last = inner[start..].trim();
-    if !last.is_empty() {
-        elements.push(last.to_string());
-    }
-
-    elements
+/// `ty` as it can be named inside a generated wrapper: named lifetimes become
+/// `'_`, and `Self` and type parameters are replaced as given by `subst`.
+fn ring_type(ty: &Type, subst: &TypeSubst) -> Type {
+    let mut ty = ty.clone();
+    normalize_type(&mut ty, subst);
+    ty
 }
 
-fn extract_box_inner(ty: &str) -> Option<String> {
-    let ty = ty.trim();
-    if ty.starts_with("Box <") {
-        Some(ty[5..ty.len() - 1].trim().to_string())
-    } else if ty.starts_with("Box<") {
-        Some(ty[4..ty.len() - 1].trim().to_string())
-    } else {
-        None
+fn normalize_type(ty: &mut Type, subst: &TypeSubst) {
+    match ty {
+        Type::Reference(r) => {
+            r.lifetime = None;
+            normalize_type(&mut r.elem, subst);
+        }
+        Type::Path(p) => {
+            if p.qself.is_none() {
+                if let Some(replacement) = subst.get(&p.path) {
+                    *ty = replacement.clone();
+                    return;
+                }
+            }
+            for segment in p.path.segments.iter_mut() {
+                let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments else {
+                    continue;
+                };
+                for arg in args.args.iter_mut() {
+                    match arg {
+                        syn::GenericArgument::Lifetime(lt) => {
+                            *lt = syn::Lifetime::new("'_", lt.span());
+                        }
+                        syn::GenericArgument::Type(t) => normalize_type(t, subst),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Type::Slice(s) => normalize_type(&mut s.elem, subst),
+        Type::Array(a) => normalize_type(&mut a.elem, subst),
+        Type::Paren(p) => normalize_type(&mut p.elem, subst),
+        Type::Group(g) => normalize_type(&mut g.elem, subst),
+        Type::Tuple(t) => {
+            for elem in t.elems.iter_mut() {
+                normalize_type(elem, subst);
+            }
+        }
+        Type::ImplTrait(t) => normalize_bounds(&mut t.bounds, subst),
+        Type::TraitObject(t) => normalize_bounds(&mut t.bounds, subst),
+        _ => {}
     }
 }
 
-fn extract_hashmap_kv(ty: &str) -> Option<(String, String)> {
-    let ty = ty.trim();
-
-    let hashmap_start = ty.find("HashMap")?;
-    let rest = &ty[hashmap_start + 7..];
-
-    let angle_start = rest.find('<')?;
-    let angle_end = rest.rfind('>')?;
-    let inner = &rest[angle_start + 1..angle_end];
-
-    let mut depth = 0;
-    for (i, c) in inner.char_indices() {
-        match c {
-            '<' => depth += 1,
-            '>' => depth -= 1,
-            ',' if depth == 0 => {
-                let key = inner[..i].trim().to_string();
-                let value = inner[i + 1..].trim().to_string();
-                return Some((key, value));
+/// Normalize the argument and return types of `Fn(..) -> ..` bounds.
+fn normalize_bounds(
+    bounds: &mut syn::punctuated::Punctuated<syn::TypeParamBound, Token![+]>,
+    subst: &TypeSubst,
+) {
+    for bound in bounds.iter_mut() {
+        let syn::TypeParamBound::Trait(t) = bound else {
+            continue;
+        };
+        for segment in t.path.segments.iter_mut() {
+            let syn::PathArguments::Parenthesized(args) = &mut segment.arguments else {
+                continue;
+            };
+            for input in args.inputs.iter_mut() {
+                normalize_type(input, subst);
+            }
+            if let syn::ReturnType::Type(_, ty) = &mut args.output {
+                normalize_type(ty, subst);
             }
-            _ => {}
         }
     }
-    None
 }
 
-fn extract_slice_inner(ty: &str) -> Option<String> {
-    let ty = ty.trim();
-    if ty.starts_with("& [") {
-        Some(ty[3..ty.len() - 1].trim().to_string())
-    } else if ty.starts_with("&[") {
-        Some(ty[2..ty.len() - 1].trim().to_string())
-    } else {
-        None
+/// The last path segment of `ty`, e.g. `Option` for `std::option::Option<T>`.
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(p) if p.qself.is_none() => p.path.segments.last(),
+        Type::Group(g) => last_segment(&g.elem),
+        Type::Paren(p) => last_segment(&p.elem),
+        _ => None,
     }
 }
 
-fn extract_struct_name(ty: &str) -> String {
-    ty.trim()
-        .trim_start_matches('&')
-        .trim()
-        .trim_start_matches("mut ")
-        .trim()
-        .to_string()
+/// The type arguments of the last path segment: `[f64]` for `Stack<f64>`.
+fn type_args(ty: &Type) -> Vec<&Type> {
+    match last_segment(ty).map(|s| &s.arguments) {
+        Some(syn::PathArguments::AngleBracketed(args)) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(t) => Some(t),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
 }
 
-fn struct_type_const(struct_name: &str) -> syn::Ident {
-    format_ident!("{}_TYPE", struct_name.to_uppercase())
+fn is_option(ty: &Type) -> bool {
+    last_segment(ty).is_some_and(|s| s.ident == "Option")
 }
 
-struct ParamBinding {
-    check: TokenStream2,
-    get: TokenStream2,
-    arg: TokenStream2,
+/// The `T` of an `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    if !is_option(ty) {
+        return None;
+    }
+    type_args(ty).first().copied()
 }
 
-fn is_mut_struct_ref(ty: &str) -> bool {
-    let ty = ty.trim();
-    ty.starts_with("& mut ") && is_struct_type(&ty[6..])
+/// Byte types passed as binary-safe Ring strings, like `RingBytes`, unless
+/// marked `#[ring(list)]`.
+#[derive(Clone, Copy, PartialEq)]
+enum ByteBuffer {
+    /// `Vec<u8>`
+    Vec,
+    /// `Box<[u8]>`
+    Boxed,
+    /// `&[u8]`
+    Slice,
+    /// A `Bytes`-like type: `From<Vec<u8>>` and `Deref<Target = [u8]>`.
+    Bytes,
 }
 
-fn generate_param_binding(name: &syn::Ident, ty: &Type, idx: i32) -> ParamBinding {
-    let type_str = quote!(#ty).to_string();
-
-    if is_number_type(&type_str) {
-        let cast = get_number_cast(&type_str);
-        ParamBinding {
-            check: quote! { ring_check_number!(p, #idx); },
-            get: quote! { let #name = ring_get_number!(p, #idx) as #cast; },
-            arg: quote! { #name },
-        }
-    } else if is_string_type(&type_str) {
-        ParamBinding {
-            check: quote! { ring_check_string!(p, #idx); },
-            get: quote! { let #name = ring_get_string!(p, #idx); },
-            arg: quote! { #name },
-        }
-    } else if type_str == "bool" {
-        ParamBinding {
-            check: quote! { ring_check_number!(p, #idx); },
-            get: quote! { let #name = ring_get_number!(p, #idx) != 0.0; },
-            arg: quote! { #name },
-        }
-    } else if is_mut_struct_ref(&type_str) {
-        let struct_name_str = extract_struct_name(&type_str);
-        let struct_ident = format_ident!("{}", struct_name_str);
-        let type_const = struct_type_const(&struct_name_str);
-        let ptr_name = format_ident!("__ptr_{}", name);
-        ParamBinding {
-            check: quote! { ring_check_cpointer!(p, #idx); },
-            get: quote! { let #ptr_name = ring_get_cpointer!(p, #idx, #type_const); },
-            arg: quote! { unsafe { &mut *(#ptr_name as *mut #struct_ident) } },
-        }
-    } else if is_struct_ref(&type_str) {
-        let struct_name_str = extract_struct_name(&type_str);
-        let struct_ident = format_ident!("{}", struct_name_str);
-        let type_const = struct_type_const(&struct_name_str);
-        let ptr_name = format_ident!("__ptr_{}", name);
-        ParamBinding {
-            check: quote! { ring_check_cpointer!(p, #idx); },
-            get: quote! { let #ptr_name = ring_get_cpointer!(p, #idx, #type_const); },
-            arg: quote! { unsafe { &*(#ptr_name as *const #struct_ident) } },
-        }
-    } else if is_vec_type(&type_str) {
-        generate_vec_param_binding(name, &type_str, idx)
-    } else if is_option_type(&type_str) {
-        generate_option_param_binding(name, &type_str, idx)
-    } else if is_slice_type(&type_str) {
-        generate_slice_param_binding(name, &type_str, idx)
-    } else if is_struct_type(&type_str) {
-        let struct_name_str = extract_struct_name(&type_str);
-        let struct_ident = format_ident!("{}", struct_name_str);
-        let type_const = struct_type_const(&struct_name_str);
-        let ptr_name = format_ident!("__ptr_{}", name);
-        ParamBinding {
-            check: quote! { ring_check_cpointer!(p, #idx); },
-            get: quote! {
-                let #ptr_name = ring_get_cpointer!(p, #idx, #type_const);
-                let #name = unsafe { (*( #ptr_name as *const #struct_ident)).clone() };
-            },
-            arg: quote! { #name },
-        }
-    } else {
-        ParamBinding {
-            check: quote! { ring_check_number!(p, #idx); },
-            get: quote! { let #name = ring_get_number!(p, #idx) as _; },
-            arg: quote! { #name },
-        }
+fn byte_buffer(ty: &Type) -> Option<ByteBuffer> {
+    let is_u8 = |ty: &Type| matches!(ty, Type::Path(p) if p.path.is_ident("u8"));
+    if let Type::Reference(r) = ty {
+        return match &*r.elem {
+            Type::Slice(s) if r.mutability.is_none() && is_u8(&s.elem) => Some(ByteBuffer::Slice),
+            _ => None,
+        };
+    }
+    let segment = last_segment(ty)?;
+    match (segment.ident.to_string().as_str(), type_args(ty).as_slice()) {
+        ("Vec", [elem]) if is_u8(elem) => Some(ByteBuffer::Vec),
+        ("Box", [Type::Slice(s)]) if is_u8(&s.elem) => Some(ByteBuffer::Boxed),
+        ("Bytes", []) => Some(ByteBuffer::Bytes),
+        _ => None,
     }
 }
 
-fn generate_vec_param_binding(name: &syn::Ident, type_str: &str, idx: i32) -> ParamBinding {
-    let inner = extract_vec_inner(type_str).unwrap_or_default();
+/// A byte buffer or an `Option` of one.
+fn is_byte_buffer(ty: &Type) -> bool {
+    byte_buffer(option_inner(ty).unwrap_or(ty)).is_some()
+}
 
-    if is_number_type(&inner) {
-        let cast = get_number_cast(&inner);
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #name = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isnumber(__list, __i) {
-                        #name.push(ring_list_getdouble(__list, __i) as #cast);
-                    }
-                }
-            },
-            arg: quote! { #name },
-        }
-    } else if is_string_type(&inner) {
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #name = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isstring(__list, __i) {
-                        let __cstr = ring_list_getstring(__list, __i);
-                        let __s = unsafe { std::ffi::CStr::from_ptr(__cstr).to_string_lossy().into_owned() };
-                        #name.push(__s);
-                    }
-                }
-            },
-            arg: quote! { #name },
-        }
-    } else if is_struct_type(&inner) {
-        let inner_struct_name = extract_struct_name(&inner);
-        let inner_struct_ident = format_ident!("{}", inner_struct_name);
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #name = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    let __ptr = if ring_list_ispointer(__list, __i) {
-                        ring_list_getpointer(__list, __i)
-                    } else if ring_list_islist(__list, __i) {
-                        let __inner_list = ring_list_getlist(__list, __i);
-                        if ring_list_ispointer(__inner_list, 1) {
-                            ring_list_getpointer(__inner_list, 1)
-                        } else {
-                            std::ptr::null_mut()
-                        }
-                    } else {
-                        std::ptr::null_mut()
-                    };
-                    if !__ptr.is_null() {
-                        let __item = unsafe { &*(__ptr as *const #inner_struct_ident) };
-                        #name.push(__item.clone());
-                    }
-                }
-            },
-            arg: quote! { #name },
+/// Read parameter `idx` of byte buffer type `ty` (or an `Option` of one) from
+/// a string, or from a list of numbers with `#[ring(list)]`. `None` when
+/// `ring_get!(p, idx, ty)` already does that.
+fn byte_buffer_get(ty: &Type, idx: i32, passing: Passing) -> Option<TokenStream2> {
+    let list = passing == Passing::List;
+    let (inner, optional) = match option_inner(ty) {
+        Some(inner) => (inner, true),
+        None => (ty, false),
+    };
+    let (read, convert) = match (byte_buffer(inner)?, list) {
+        (ByteBuffer::Vec, false) => (quote! { RingBytes }, quote! { __bytes.0 }),
+        (ByteBuffer::Bytes, false) => (quote! { RingBytes }, quote! { <#inner>::from(__bytes.0) }),
+        (ByteBuffer::Boxed | ByteBuffer::Bytes, true) => {
+            (quote! { Vec<u8> }, quote! { <#inner>::from(__bytes) })
         }
+        _ => return None,
+    };
+    Some(if optional {
+        quote_spanned! {ty.span()=> ring_get!(p, #idx, Option<#read>).map(|__bytes| #convert) }
     } else {
-        let cast = get_number_cast(&inner);
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #name = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isnumber(__list, __i) {
-                        #name.push(ring_list_getdouble(__list, __i) as #cast);
-                    }
-                }
-            },
-            arg: quote! { #name },
-        }
-    }
+        quote_spanned! {ty.span()=> {
+            let __bytes = ring_get!(p, #idx, #read);
+            #convert
+        }}
+    })
 }
 
-fn generate_option_param_binding(name: &syn::Ident, type_str: &str, idx: i32) -> ParamBinding {
-    let inner = extract_option_inner(type_str).unwrap_or_default();
+/// What `ring_ret!` returns for `value` of byte buffer type `ty` (or an
+/// `Option` of one): a string, or a list of numbers with `#[ring(list)]`.
+/// `None` when `value` itself already returns that way.
+fn byte_buffer_ret(ty: &Type, value: TokenStream2, passing: Passing) -> Option<TokenStream2> {
+    let list = passing == Passing::List;
+    let (inner, optional) = match option_inner(ty) {
+        Some(inner) => (inner, true),
+        None => (ty, false),
+    };
+    Some(match (byte_buffer(inner)?, list, optional) {
+        (ByteBuffer::Vec | ByteBuffer::Bytes, false, false) => quote! { &#value[..] },
+        (ByteBuffer::Vec | ByteBuffer::Bytes, false, true) => quote! { #value.as_deref() },
+        (ByteBuffer::Boxed | ByteBuffer::Bytes | ByteBuffer::Slice, true, false) => {
+            quote! { #value.to_vec() }
+        }
+        (ByteBuffer::Boxed | ByteBuffer::Bytes, true, true) => {
+            quote! { #value.as_deref().map(<[u8]>::to_vec) }
+        }
+        (ByteBuffer::Slice, true, true) => quote! { #value.map(<[u8]>::to_vec) },
+        _ => return None,
+    })
+}
 
-    if is_number_type(&inner) {
-        let cast = get_number_cast(&inner);
-        ParamBinding {
-            check: quote! {},
-            get: quote! {
-                let #name = if ring_api_isstring(p, #idx) {
-                    let __s = ring_get_string!(p, #idx);
-                    if __s.is_empty() { None } else { None }
-                } else if ring_api_isnumber(p, #idx) {
-                    Some(ring_get_number!(p, #idx) as #cast)
-                } else {
-                    None
-                };
-            },
-            arg: quote! { #name },
-        }
-    } else if is_string_type(&inner) {
-        ParamBinding {
-            check: quote! {},
-            get: quote! {
-                let #name = if ring_api_isstring(p, #idx) {
-                    let __s = ring_get_string!(p, #idx);
-                    if __s.is_empty() { None } else { Some(__s.to_string()) }
-                } else {
-                    None
-                };
-            },
-            arg: quote! { #name },
-        }
-    } else if is_struct_type(&inner) {
-        let inner_struct_name = extract_struct_name(&inner);
-        let inner_struct_ident = format_ident!("{}", inner_struct_name);
-        let inner_type_const = struct_type_const(&inner_struct_name);
-        ParamBinding {
-            check: quote! {},
-            get: quote! {
-                let #name = if ring_api_isstring(p, #idx) {
-                    None
-                } else if ring_api_ispointer(p, #idx) {
-                    let __ptr = ring_get_cpointer!(p, #idx, #inner_type_const);
-                    if __ptr.is_null() {
-                        None
-                    } else {
-                        Some(unsafe { (*(__ptr as *const #inner_struct_ident)).clone() })
-                    }
-                } else {
-                    None
-                };
-            },
-            arg: quote! { #name },
-        }
-    } else {
-        let cast = get_number_cast(&inner);
-        ParamBinding {
-            check: quote! {},
-            get: quote! {
-                let #name = if ring_api_isstring(p, #idx) {
-                    None
-                } else if ring_api_isnumber(p, #idx) {
-                    Some(ring_get_number!(p, #idx) as #cast)
-                } else {
-                    None
-                };
-            },
-            arg: quote! { #name },
-        }
+fn is_slice_ref(ty: &Type) -> bool {
+    matches!(ty, Type::Reference(r) if matches!(*r.elem, Type::Slice(_)))
+}
+
+/// The `T` of a `Result<T, E>` (or a `Result<T>` alias such as `io::Result`
+/// or `RingResult`).
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let segment = last_segment(ty).filter(|s| s.ident == "Result" || s.ident == "RingResult")?;
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
     }
 }
 
-fn generate_slice_param_binding(name: &syn::Ident, type_str: &str, idx: i32) -> ParamBinding {
-    let inner = extract_slice_inner(type_str).unwrap_or_default();
-    let vec_name = format_ident!("__{}_vec", name);
+/// The trait of a `&dyn Trait` or `&mut dyn Trait` parameter.
+fn dyn_trait_name(ty: &Type) -> Option<String> {
+    let Type::Reference(r) = ty else { return None };
+    let Type::TraitObject(t) = &*r.elem else {
+        return None;
+    };
+    t.bounds.iter().find_map(|bound| match bound {
+        syn::TypeParamBound::Trait(t) => t.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    })
+}
 
-    if is_number_type(&inner) {
-        let cast = get_number_cast(&inner);
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #vec_name: Vec<#cast> = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isnumber(__list, __i) {
-                        #vec_name.push(ring_list_getdouble(__list, __i) as #cast);
-                    }
-                }
-            },
-            arg: quote! { &#vec_name[..] },
-        }
-    } else if is_string_type(&inner) {
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #vec_name: Vec<String> = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isstring(__list, __i) {
-                        let __cstr = ring_list_getstring(__list, __i);
-                        let __s = unsafe { std::ffi::CStr::from_ptr(__cstr).to_string_lossy().into_owned() };
-                        #vec_name.push(__s);
-                    }
-                }
-            },
-            arg: quote! { &#vec_name[..] },
-        }
-    } else if is_struct_type(&inner) {
-        let inner_struct_name = extract_struct_name(&inner);
-        let inner_struct_ident = format_ident!("{}", inner_struct_name);
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #vec_name: Vec<#inner_struct_ident> = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    let __ptr = if ring_list_ispointer(__list, __i) {
-                        ring_list_getpointer(__list, __i)
-                    } else {
-                        std::ptr::null_mut()
-                    };
-                    if !__ptr.is_null() {
-                        let __item = unsafe { &*(__ptr as *const #inner_struct_ident) };
-                        #vec_name.push(__item.clone());
-                    }
-                }
-            },
-            arg: quote! { &#vec_name[..] },
-        }
-    } else {
-        ParamBinding {
-            check: quote! { ring_check_list!(p, #idx); },
-            get: quote! {
-                let __list = ring_get_list!(p, #idx);
-                let __size = ring_list_getsize(__list);
-                let mut #vec_name: Vec<f64> = Vec::with_capacity(__size as usize);
-                for __i in 1..=__size {
-                    if ring_list_isnumber(__list, __i) {
-                        #vec_name.push(ring_list_getdouble(__list, __i));
-                    }
-                }
-            },
-            arg: quote! { &#vec_name[..] },
-        }
+/// The struct a parameter passes as a C pointer (`T`, `&T`, `&mut T`).
+fn pointer_struct_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Reference(r) => pointer_struct_name(&r.elem),
+        _ => last_segment(ty).map(|s| s.ident.to_string()),
     }
 }
@@ -0,0 +1,97 @@
+//! Expansion tests for `ring_extension!`. The wrappers call into Ring, so
+//! these link against the Ring library like the tests of `ring-lang-rs`.
+
+use ring_lang_codegen::ring_extension;
+use ring_lang_rs::*;
+
+ring_extension! {
+    prefix: "geo";
+
+    #[derive(Default, Clone)]
+    pub struct Point {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    #[derive(Default)]
+    pub struct Line {
+        #[ring(view)]
+        pub start: Point,
+        pub end: Point,
+    }
+
+    impl Line {
+        pub fn set_start(&mut self, start: Point) {
+            self.start = start;
+        }
+
+        pub fn move_start(&mut self, to: &Point) {
+            self.start = to.clone();
+        }
+
+        pub fn points(&self) -> Vec<Point> {
+            vec![self.start.clone(), self.end.clone()]
+        }
+    }
+
+    #[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct Style {
+        pub color: String,
+    }
+
+    #[derive(Default)]
+    pub struct Shape {
+        #[ring(serde)]
+        pub style: Style,
+        pub origin: Point,
+    }
+
+    pub fn each_point(line: &Line, f: impl Fn(f64, f64)) {
+        f(line.start.x, line.start.y);
+        f(line.end.x, line.end.y);
+    }
+
+    pub fn try_each(line: &Line, f: impl Fn(f64) -> RingResult<bool>) -> RingResult<bool> {
+        f(line.start.x)
+    }
+
+    #[ring(export)]
+    pub const MAX_POINTS: u32 = 64;
+
+    #[ring(export, name = "origin_x")]
+    pub static ORIGIN_X: f64 = 0.0;
+
+    pub const INTERNAL: u32 = 1;
+}
+
+type Wrapper = extern "C" fn(*mut std::ffi::c_void);
+
+/// Verify wrappers are generated for methods, view fields and callbacks, and
+/// constants keep their Rust values
+#[test]
+fn test_generated_wrappers() {
+    let _: [Wrapper; 6] = [
+        ring_geo_line_set_start,
+        ring_geo_line_move_start,
+        ring_geo_line_get_start,
+        ring_geo_line_get_end,
+        ring_geo_each_point,
+        ring_geo_try_each,
+    ];
+    assert_eq!(MAX_POINTS, 64);
+    assert_eq!(ORIGIN_X, 0.0);
+    assert_eq!(INTERNAL, 1);
+}
+
+/// Verify returned structs are wrapped in their Ring classes
+#[test]
+fn test_ring_classes() {
+    assert!(RING_CLASSES.contains("Func geo_point_fromPointer pointer\n"));
+    assert!(RING_CLASSES.contains("Func geo_point_fromPointers pointers\n"));
+    assert!(RING_CLASSES.contains("Class Line\n"));
+    assert!(RING_CLASSES.contains("return geo_point_fromPointer(geo_line_get_start(pObject))"));
+    assert!(RING_CLASSES.contains("return geo_point_fromPointers(geo_line_points(pObject))"));
+    // Serde fields are plain Ring data.
+    assert!(RING_CLASSES.contains("return geo_shape_get_style(pObject)\n"));
+    assert!(RING_CLASSES.contains("return geo_point_fromPointer(geo_shape_get_origin(pObject))"));
+}
@@ -0,0 +1,17 @@
+use ring_lang_codegen::ring_extension;
+use ring_lang_rs::*;
+
+ring_extension! {
+    prefix: "t";
+
+    #[derive(Default)]
+    pub struct Handle {
+        pub id: u32,
+    }
+
+    pub fn close(handle: Handle) -> u32 {
+        handle.id
+    }
+}
+
+fn main() {}
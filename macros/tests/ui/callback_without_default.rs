@@ -0,0 +1,20 @@
+use ring_lang_codegen::ring_extension;
+use ring_lang_rs::*;
+
+pub struct Answer(f64);
+
+impl FromRing<'_> for Answer {
+    fn from_item(item: &Item) -> RingResult<Self> {
+        f64::from_item(item).map(Answer)
+    }
+}
+
+ring_extension! {
+    prefix: "t";
+
+    pub fn ask(f: impl Fn() -> Answer) -> f64 {
+        f().0
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use ring_lang_codegen::ring_extension;
+
+ring_extension! {
+    prefix: "t";
+    classes: "t.ring";
+
+    pub fn one() -> u32 {
+        1
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use ring_lang_codegen::ring_extension;
+
+ring_extension! {
+    prefix: "t";
+
+    #[ring(export)]
+    const PRIVATE: u32 = 1;
+
+    #[ring(name = "renamed")]
+    pub const NOT_EXPORTED: u32 = 2;
+
+    #[ring(export)]
+    pub static mut COUNTER: u32 = 0;
+}
+
+fn main() {}
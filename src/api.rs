@@ -61,6 +61,10 @@ pub fn ring_api_getstring(p: *mut c_void, n: c_int) -> *const c_char {
     unsafe { ffi::ring_vm_api_getstring(p, n) }
 }
 
+/// String parameter `n`, or `""` if it is not valid UTF-8.
+///
+/// The string is only valid during the extension function call, despite the
+/// `'static`; [`ring_api_getstring_str_ref`] ties it to the call.
 #[inline]
 pub fn ring_api_getstring_str(p: *mut c_void, n: c_int) -> &'static str {
     unsafe {
@@ -73,6 +77,9 @@ pub fn ring_api_getstring_str(p: *mut c_void, n: c_int) -> &'static str {
     }
 }
 
+/// Bytes of string parameter `n`, valid only during the call like
+/// [`ring_api_getstring_str`]; [`ring_api_getstring_bytes_ref`] ties them to
+/// the call.
 #[inline]
 pub fn ring_api_getstring_bytes(p: *mut c_void, n: c_int) -> &'static [u8] {
     unsafe {
@@ -86,6 +93,21 @@ pub fn ring_api_getstring_bytes(p: *mut c_void, n: c_int) -> &'static [u8] {
     }
 }
 
+/// [`ring_api_getstring_str`] borrowing `p` from the variable holding the
+/// pointer, so the string can't outlive the extension function call it
+/// points into.
+#[inline]
+pub fn ring_api_getstring_str_ref(p: &*mut c_void, n: c_int) -> &str {
+    ring_api_getstring_str(*p, n)
+}
+
+/// [`ring_api_getstring_bytes`] borrowing `p`, like
+/// [`ring_api_getstring_str_ref`].
+#[inline]
+pub fn ring_api_getstring_bytes_ref(p: &*mut c_void, n: c_int) -> &[u8] {
+    ring_api_getstring_bytes(*p, n)
+}
+
 #[inline]
 pub fn ring_api_getstringsize(p: *mut c_void, n: c_int) -> c_uint {
     unsafe { ffi::ring_vm_api_getstringsize(p, n) }
@@ -356,6 +378,30 @@ pub fn ring_api_error_str(p: *mut c_void, s: &str) {
     }
 }
 
+/// Run an extension function body, turning a Rust panic into a Ring runtime
+/// error instead of unwinding into the VM. Used by [`ring_func!`](crate::ring_func).
+///
+/// Without unwinding (`panic = "abort"`) or with the `no-catch-unwind` feature
+/// the body is called directly.
+#[doc(hidden)]
+#[inline]
+pub fn ring_func_guard<F: FnOnce(*mut c_void)>(p: *mut c_void, body: F) {
+    #[cfg(all(panic = "unwind", not(feature = "no-catch-unwind")))]
+    {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| body(p))) {
+            let msg = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            ring_api_error_str(p, &format!("Rust panic: {}", msg.replace('\0', "")));
+        }
+    }
+    #[cfg(not(all(panic = "unwind", not(feature = "no-catch-unwind"))))]
+    body(p)
+}
+
 pub fn ring_register_function(state: RingState, name: &[u8], func: RingFunc) {
     unsafe {
         ffi::ring_vm_funcregister2(state, name.as_ptr() as *const c_char, func);
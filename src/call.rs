@@ -0,0 +1,83 @@
+//! Typed context for extension function calls.
+//!
+//! [`RingCall`] wraps the `p` pointer a Ring function receives. Values it
+//! hands out (borrowed strings, list views) are tied to the call, so they
+//! can't outlive the VM data they point into.
+//!
+//! ```rust,ignore
+//! ring_func!(ring_repeat, |call: RingCall| {
+//!     let text: &str = match call.arg(1) {
+//!         Ok(s) => s,
+//!         Err(e) => return call.error(&e.message),
+//!     };
+//!     let times: usize = call.arg(2).unwrap_or(1);
+//!     call.ret(text.repeat(times));
+//! });
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::api::{ring_api_callerscope, ring_api_error_str, ring_api_newlist, ring_api_paracount};
+use crate::convert::{FromRing, IntoRing};
+use crate::ffi_types::{c_int, c_void};
+use crate::{ListMut, ListRef, RingError, RingResult};
+
+/// The context of one extension function call.
+pub struct RingCall<'a> {
+    p: *mut c_void,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl RingCall<'_> {
+    /// Wrap the pointer passed to an extension function.
+    ///
+    /// # Safety
+    ///
+    /// `p` must be the pointer Ring passed to the currently running function,
+    /// and the context must not be used after that function returns.
+    pub unsafe fn from_raw(p: *mut c_void) -> Self {
+        Self {
+            p,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The raw pointer, for the `ring_api_*` functions and macros.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.p
+    }
+
+    /// Number of arguments passed by the caller.
+    pub fn args_len(&self) -> usize {
+        ring_api_paracount(self.p) as usize
+    }
+
+    /// Argument `n` (1-based, as in Ring) converted to `T`.
+    ///
+    /// Borrowed values such as `&str` live as long as the borrow of the call.
+    pub fn arg<'s, T: FromRing<'s>>(&'s self, n: usize) -> RingResult<T> {
+        let n = c_int::try_from(n).map_err(|_| RingError::new(format!("missing parameter {n}")))?;
+        T::from_param(&self.p, n)
+    }
+
+    /// Set the function's return value.
+    pub fn ret<T: IntoRing>(&self, value: T) {
+        value.ret(self.p);
+    }
+
+    /// Raise a Ring runtime error. The function should return right after.
+    pub fn error(&self, msg: &str) {
+        ring_api_error_str(self.p, msg);
+    }
+
+    /// The local scope of the Ring code that called this function.
+    pub fn caller_scope(&self) -> ListRef<'_> {
+        unsafe { ListRef::from_raw(ring_api_callerscope(self.p)) }
+    }
+
+    /// A new list owned by the VM and freed after the call; return it with
+    /// [`ret`](Self::ret).
+    pub fn new_list(&self) -> ListMut<'_> {
+        unsafe { ListMut::from_raw(ring_api_newlist(self.p)) }
+    }
+}
@@ -0,0 +1,93 @@
+//! Ring functions passed into Rust.
+//!
+//! Ring has no closures: a function value is its name, and an anonymous
+//! `func` evaluates to the generated name. [`RingCallback`] reads that name
+//! as a parameter and keeps the VM it came from, so Rust can call back into
+//! Ring while the extension function runs.
+//!
+//! ```rust,ignore
+//! ring_func!(ring_apply, |p| {
+//!     ring_check_paracount!(p, 2);
+//!     let f = ring_get!(p, 1, RingCallback);
+//!     let items = ring_get!(p, 2, Vec<f64>);
+//!     let mut out = Vec::with_capacity(items.len());
+//!     for x in items {
+//!         match f.call::<f64, _>((x,)) {
+//!             Ok(y) => out.push(y),
+//!             Err(e) => return ring_error!(p, &e.message),
+//!         }
+//!     }
+//!     ring_ret!(p, out);
+//! });
+//! ```
+//!
+//! ```ring
+//! ? apply(func x { return x * 2 }, [1, 2, 3])
+//! ? apply("myfunc", [1, 2, 3])
+//! ```
+
+use crate::convert::{FromRing, RingArgs};
+use crate::ffi::Item;
+use crate::ffi_types::{c_int, c_void};
+use crate::interpreter::call_catching;
+use crate::vm::ring_vm_funcexists;
+use crate::{RingError, RingResult, RingVM};
+
+/// A Ring function (by name) and the VM to call it in.
+///
+/// Only valid while the VM is alive. Calls must happen on the thread running
+/// the VM, normally inside the extension function that received it.
+#[derive(Debug, Clone)]
+pub struct RingCallback {
+    vm: RingVM,
+    name: String,
+}
+
+impl RingCallback {
+    /// Refer to function `name` in `vm`, checking that it exists.
+    pub fn new(vm: RingVM, name: &str) -> RingResult<Self> {
+        let name = name.to_ascii_lowercase();
+        if !ring_vm_funcexists(vm, &name) {
+            return Err(RingError::new(format!("function not found: {name}")));
+        }
+        Ok(Self { vm, name })
+    }
+
+    /// The (lowercase) function name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn vm(&self) -> RingVM {
+        self.vm
+    }
+
+    /// Call the function and convert its return value.
+    ///
+    /// `args` is `()` or a tuple: `cb.call::<bool, _>((a, b))`. The call runs
+    /// under `try`/`catch` like [`Ring::eval`](crate::Ring::eval), so a runtime
+    /// error in the function comes back as the `Err` instead of ending the
+    /// program. Arguments and the return value are copied, so lists changed by
+    /// the function are not seen by the caller.
+    pub fn call<R, A>(&self, args: A) -> RingResult<R>
+    where
+        R: for<'a> FromRing<'a>,
+        A: RingArgs,
+    {
+        call_catching(self.vm, &self.name, args)
+    }
+}
+
+impl<'a> FromRing<'a> for RingCallback {
+    fn from_item(_item: &'a Item) -> RingResult<Self> {
+        Err(RingError::new(
+            "a callback can only be read from a function parameter",
+        ))
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        let name = <&str>::from_param(p, n)?;
+        // Extension functions receive the VM as their `p` pointer.
+        Self::new(*p as RingVM, name)
+    }
+}
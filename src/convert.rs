@@ -0,0 +1,1308 @@
+//! Conversions between Rust values and Ring items.
+//!
+//! [`FromRing`] reads a value out of a Ring [`Item`] and [`IntoRing`] writes one
+//! into an item. Every value a list, variable or stack slot holds is an item, so
+//! the same two traits back [`Ring::get_global`](crate::Ring::get_global),
+//! [`Ring::call`](crate::Ring::call), the `ring_get!`/`ring_ret!` macros and the
+//! code generated by `ring_extension!`.
+//!
+//! | Rust | Ring |
+//! |------|------|
+//! | `f64`, `f32`, integers, `bool` | number |
+//! | `String`, `&str`, `Cow<str>`, `&[u8]`, `Box<[u8]>`, [`RingBytes`] | string (binary-safe) |
+//! | `char` | one-character string |
+//! | `Vec<T>` (including `Vec<u8>`), tuples | list |
+//! | `HashMap<K, V>`, `BTreeMap<K, V>`, `IndexMap<K, V>` | list of `[key, value]` pairs |
+//! | `Option<T>` | value, or empty string for `None` (a null C pointer for [`RingPointer`] types, an empty list for byte buffers) |
+//! | [`CPointer`] | C pointer (`[pointer, type, status]` list) |
+//! | [`RingPointer`] types, `&T`, `&mut T` | C pointer to a boxed `T` (managed, also inside lists) |
+//! | [`RingValue`](crate::RingValue) | any value |
+//! | `RingSerde<T>` (feature `serde`) | `T` converted with serde |
+//!
+//! Maps read both pair lists and Ring's `[:key = value]` lists, which are the
+//! same thing. Written maps with string keys are indexed, so `list[:key]`
+//! is a hash lookup. `BTreeMap` is written in key order and `IndexMap`
+//! (feature `indexmap`) in insertion order.
+//!
+//! Implement the traits for your own types to accept and return them from
+//! extension functions:
+//!
+//! ```rust,ignore
+//! struct Point { x: f64, y: f64 }
+//!
+//! impl<'a> FromRing<'a> for Point {
+//!     fn from_item(item: &'a Item) -> RingResult<Self> {
+//!         let (x, y) = <(f64, f64)>::from_item(item)?;
+//!         Ok(Point { x, y })
+//!     }
+//! }
+//!
+//! impl IntoRing for Point {
+//!     fn into_item(self, item: RingItem) {
+//!         (self.x, self.y).into_item(item);
+//!     }
+//! }
+//!
+//! ring_func!(ring_midpoint, |p| {
+//!     ring_check_paracount!(p, 2);
+//!     let a = ring_get!(p, 1, Point);
+//!     let b = ring_get!(p, 2, Point);
+//!     ring_ret!(p, Point { x: (a.x + b.x) / 2.0, y: (a.y + b.y) / 2.0 });
+//! });
+//! ```
+
+#[cfg(feature = "indexmap")]
+use indexmap::IndexMap;
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::sync::Mutex;
+
+use crate::api::{
+    ring_api_getlist, ring_api_getnumber, ring_api_getstring_bytes_ref, ring_api_islist,
+    ring_api_isnumber, ring_api_isstring, ring_api_newlist, ring_api_paracount, ring_api_retlist,
+    ring_api_retmanagedcpointer, ring_api_retnumber, ring_api_retstring2,
+};
+use crate::ffi::{
+    self, ITEMTYPE_LIST, ITEMTYPE_NUMBER, ITEMTYPE_POINTER, ITEMTYPE_STRING, Item, ItemData,
+    RingItem,
+};
+use crate::ffi_types::{c_int, c_uint, c_void};
+use crate::item::{
+    ring_item_getnumber, ring_item_setdouble, ring_item_setstring2, ring_item_settype,
+};
+use crate::list::{
+    ring_list_addint, ring_list_addpointer, ring_list_addstring2, ring_list_genhashtable2,
+    ring_list_getint, ring_list_getitem, ring_list_getpointer, ring_list_getsize,
+    ring_list_iscpointerlist, ring_list_isobject, ring_list_newitem, ring_list_setint,
+};
+use crate::value::ring_object_setself;
+use crate::{
+    RING_CPOINTER_POINTER, RING_CPOINTERSTATUS_NOTCOPIED, RING_OBJTYPE_LISTITEM,
+    RING_OBJTYPE_SUBLIST, RING_OBJTYPE_VARIABLE, RING_VAR_PVALUETYPE, RING_VAR_TYPE,
+    RING_VAR_VALUE, RING_VM_LIST, RING_VM_NUMBER, RING_VM_POINTER, RING_VM_STRING, RingError,
+    RingList, RingResult,
+};
+
+/// Read a Rust value from a Ring item.
+///
+/// The lifetime lets borrowed types such as `&str` point into the item's string
+/// buffer; owned types implement `FromRing<'a>` for every `'a`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be read from a Ring value",
+    label = "no `FromRing` impl for this type",
+    note = "implement `FromRing`, or use `ring_pointer_type!` to pass it as a C pointer"
+)]
+pub trait FromRing<'a>: Sized {
+    fn from_item(item: &'a Item) -> RingResult<Self>;
+
+    /// Read parameter `n` (1-based) of an extension function call.
+    ///
+    /// `p` is borrowed from the variable holding the pointer, so borrowed
+    /// values such as `&str` can't outlive the call.
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        Self::from_item(param_item(p, n)?)
+    }
+
+    /// Whether `item` holds the `None` of an `Option<Self>`: nothing, an
+    /// empty string or a null C pointer.
+    #[doc(hidden)]
+    fn is_none(item: &Item) -> bool {
+        is_none_item(item)
+    }
+
+    /// Whether parameter `n` is the `None` of an `Option<Self>`, as
+    /// [`is_none`](Self::is_none). A missing parameter is always `None`.
+    #[doc(hidden)]
+    fn is_none_param(p: &*mut c_void, n: c_int) -> bool {
+        (ring_api_isstring(*p, n) && ring_api_getstring_bytes_ref(p, n).is_empty())
+            || (ring_api_islist(*p, n) && CPointer::from_param(p, n).is_ok_and(|c| c.ptr.is_null()))
+    }
+}
+
+/// Write a Rust value into a Ring item, replacing its previous content.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned to Ring",
+    label = "no `IntoRing` impl for this type",
+    note = "implement `IntoRing`, or use `ring_pointer_type!` to pass it as a C pointer"
+)]
+pub trait IntoRing {
+    fn into_item(self, item: RingItem);
+
+    /// Return the value from an extension function call.
+    fn ret(self, p: *mut c_void)
+    where
+        Self: Sized,
+    {
+        let item = list_newitem(ring_api_newlist(p));
+        self.into_item(item);
+        ret_item(p, item);
+    }
+
+    /// Write the `None` of an `Option<Self>`: an empty string, a null C
+    /// pointer for [`RingPointer`] types or an empty list for byte buffers.
+    #[doc(hidden)]
+    fn none_into_item(item: RingItem)
+    where
+        Self: Sized,
+    {
+        ring_item_setstring2(item, b"");
+    }
+}
+
+pub(crate) fn ret_item(p: *mut c_void, item: RingItem) {
+    let item = unsafe { &*item };
+    match item.nType() {
+        ITEMTYPE_NUMBER => {
+            ring_api_retnumber(p, ring_item_getnumber(item as *const Item as RingItem))
+        }
+        ITEMTYPE_STRING => ring_api_retstring2(p, item_bytes(item).unwrap_or_default()),
+        ITEMTYPE_LIST => ring_api_retlist(p, unsafe { item.data.pList }),
+        _ => {}
+    }
+}
+
+/// Arguments for a Ring function call: `()` or a tuple of [`IntoRing`] values.
+///
+/// Each argument is written into the item returned by `next`.
+pub trait RingArgs {
+    fn write_args(self, next: &mut dyn FnMut() -> RingItem);
+}
+
+/// A C pointer as Ring stores it: the address and its type name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CPointer {
+    pub ptr: *mut c_void,
+    pub type_name: String,
+}
+
+impl CPointer {
+    pub fn new(ptr: *mut c_void, type_name: impl Into<String>) -> Self {
+        Self {
+            ptr,
+            type_name: type_name.into(),
+        }
+    }
+}
+
+/// A Rust type that Ring holds as a typed C pointer to a boxed value.
+///
+/// Implement it with [`ring_pointer_type!`](crate::ring_pointer_type), which
+/// also adds the conversions: `&T` and `&mut T` borrow the boxed value, `T`
+/// reads a clone of it (when `T: Clone`) and returning a `T` boxes it as a
+/// managed pointer that Ring's GC drops.
+/// `ring_extension!` does this for every struct it exports.
+pub trait RingPointer: Sized + 'static {
+    /// The type name stored with the pointer, NUL-terminated.
+    const RING_TYPE: &'static [u8];
+}
+
+fn pointer_type_name<T: RingPointer>() -> &'static str {
+    let name = T::RING_TYPE.strip_suffix(b"\0").unwrap_or(T::RING_TYPE);
+    std::str::from_utf8(name).unwrap_or("?")
+}
+
+/// The boxed value a C pointer item refers to, checking its type name.
+fn pointer_from_item<T: RingPointer>(item: &Item) -> RingResult<*mut T> {
+    let name = pointer_type_name::<T>();
+    let pointer =
+        CPointer::from_item(item).map_err(|_| type_error(&format!("a {name} pointer"), item))?;
+    if pointer.type_name != name {
+        return Err(RingError::new(format!(
+            "expected a {name} pointer, found a {} pointer",
+            pointer.type_name
+        )));
+    }
+    if pointer.ptr.is_null() {
+        return Err(RingError::new(format!("{name} pointer is null")));
+    }
+    Ok(pointer.ptr as *mut T)
+}
+
+/// Whether the item is a C pointer with `T`'s type name.
+#[doc(hidden)]
+pub fn ring_pointer_is<T: RingPointer>(item: &Item) -> bool {
+    CPointer::from_item(item).is_ok_and(|pointer| pointer.type_name == pointer_type_name::<T>())
+}
+
+/// The error for an item that is none of a trait's implementors.
+#[doc(hidden)]
+pub fn ring_pointer_dyn_error(item: &Item, trait_name: &str) -> RingError {
+    match CPointer::from_item(item) {
+        Ok(pointer) => RingError::new(format!(
+            "expected a {trait_name} pointer, found a {} pointer",
+            pointer.type_name
+        )),
+        Err(_) => type_error(&format!("a {trait_name} pointer"), item),
+    }
+}
+
+/// Writes the value as a managed C pointer, like [`ring_pointer_ret`], so
+/// structs in returned lists are dropped by the GC too.
+#[doc(hidden)]
+pub fn ring_pointer_into_item<T: RingPointer>(value: T, item: RingItem) {
+    let ptr = Box::into_raw(Box::new(value)) as *mut c_void;
+    CPointer::new(ptr, pointer_type_name::<T>()).into_item(item);
+    let list = unsafe { (*item).data.pList };
+    let pointer = ring_list_getitem(list, RING_CPOINTER_POINTER);
+    unsafe { (*pointer).pGCFreeFunc = Some(ring_pointer_free::<T>) };
+}
+
+/// Writes a null C pointer of type `T`, the `None` of an `Option<T>`.
+#[doc(hidden)]
+pub fn ring_pointer_none_into_item<T: RingPointer>(item: RingItem) {
+    CPointer::new(std::ptr::null_mut(), pointer_type_name::<T>()).into_item(item);
+}
+
+fn is_null_pointer(item: &Item) -> bool {
+    CPointer::from_item(item).is_ok_and(|pointer| pointer.ptr.is_null())
+}
+
+/// Whether the item holds the `None` of an `Option`: nothing, an empty
+/// string or a null C pointer.
+pub(crate) fn is_none_item(item: &Item) -> bool {
+    match deref_item(item).nType() {
+        ffi::ITEMTYPE_NOTHING => true,
+        ITEMTYPE_STRING => item_bytes(item).is_ok_and(<[u8]>::is_empty),
+        _ => is_null_pointer(item),
+    }
+}
+
+/// Returns the value as a managed C pointer: Ring's GC drops it with
+/// [`ring_pointer_free`] once the last reference is gone.
+#[doc(hidden)]
+pub fn ring_pointer_ret<T: RingPointer>(value: T, p: *mut c_void) {
+    let ptr = Box::into_raw(Box::new(value)) as *mut c_void;
+    ring_api_retmanagedcpointer(p, ptr, T::RING_TYPE, ring_pointer_free::<T>);
+}
+
+/// Free function for a managed C pointer to a boxed `T`, for
+/// `ring_api_retmanagedcpointer`. Null pointers (already deleted with
+/// `ring_api_setnullpointer`) are ignored.
+pub extern "C" fn ring_pointer_free<T: RingPointer>(_state: *mut c_void, ptr: *mut c_void) {
+    ring_pointer_drop(ptr as *mut T);
+}
+
+/// Drop the boxed `T` a C pointer refers to, as its `_delete` function and
+/// GC free do.
+///
+/// A pointer returned by [`ring_pointer_view_ret`] only releases its view, and
+/// a value with live views is dropped once the last one is released.
+pub fn ring_pointer_drop<T: RingPointer>(ptr: *mut T) {
+    if ptr.is_null() {
+        return;
+    }
+    let key = view_key(ptr);
+    let mut views = VIEWS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(owner) = views.release_view(key) {
+        let drop_owner = views.release_owner(owner);
+        drop(views);
+        if let Some(drop_owner) = drop_owner {
+            drop_owner(owner.0 as *mut c_void);
+        }
+        return;
+    }
+    if let Some(owner) = views.owners.get_mut(&key) {
+        owner.deleted = Some(drop_box::<T>);
+        return;
+    }
+    drop(views);
+    drop(unsafe { Box::from_raw(ptr) });
+}
+
+fn drop_box<T>(ptr: *mut c_void) {
+    drop(unsafe { Box::from_raw(ptr as *mut T) });
+}
+
+/// A boxed value or a view, by address and type: a struct and its first
+/// field share an address.
+type ViewKey = (usize, TypeId);
+
+fn view_key<T: RingPointer>(ptr: *mut T) -> ViewKey {
+    (ptr as usize, TypeId::of::<T>())
+}
+
+/// A boxed value with views into it.
+struct ViewOwner {
+    views: usize,
+    /// Set when the value was deleted while views were alive.
+    deleted: Option<fn(*mut c_void)>,
+}
+
+/// Views returned by [`ring_pointer_view_ret`] and the values they keep
+/// alive.
+struct Views {
+    /// Each view's owner, and the number of Ring pointers to the view.
+    views: BTreeMap<ViewKey, (ViewKey, usize)>,
+    owners: BTreeMap<ViewKey, ViewOwner>,
+}
+
+impl Views {
+    /// Release one pointer to `key` if it is a view, returning its owner.
+    fn release_view(&mut self, key: ViewKey) -> Option<ViewKey> {
+        let (owner, count) = self.views.get_mut(&key)?;
+        let owner = *owner;
+        *count -= 1;
+        if *count == 0 {
+            self.views.remove(&key);
+        }
+        Some(owner)
+    }
+
+    /// Release one view of `owner`, returning its drop function if that was
+    /// the last one and the owner was deleted.
+    fn release_owner(&mut self, owner: ViewKey) -> Option<fn(*mut c_void)> {
+        let state = self.owners.get_mut(&owner)?;
+        state.views -= 1;
+        if state.views > 0 {
+            return None;
+        }
+        self.owners.remove(&owner)?.deleted
+    }
+}
+
+static VIEWS: Mutex<Views> = Mutex::new(Views {
+    views: BTreeMap::new(),
+    owners: BTreeMap::new(),
+});
+
+/// Return a C pointer to `field`, a value inside the boxed `parent`, without
+/// copying it: changes made through it from Ring change the parent.
+///
+/// The parent (or the value owning it, if `parent` is itself a view) stays
+/// alive until every pointer to the view is released, even if it is deleted
+/// first. Deleting the view only releases it.
+///
+/// ```rust,ignore
+/// ring_func!(ring_outer_get_inner, |p| {
+///     ring_check_paracount!(p, 1);
+///     let outer = ring_get!(p, 1, &mut Outer);
+///     let inner: *mut Inner = &mut outer.inner;
+///     ring_pointer_view_ret(p, outer, inner);
+/// });
+/// ```
+///
+/// Ring can then pass the view and its parent to the same function. Check
+/// with [`ring_pointer_params_overlap`] before borrowing both when one of the
+/// borrows is `&mut`.
+pub fn ring_pointer_view_ret<P: RingPointer, T: RingPointer>(
+    p: *mut c_void,
+    parent: *mut P,
+    field: *mut T,
+) {
+    add_view(parent, field);
+    ring_api_retmanagedcpointer(
+        p,
+        field as *mut c_void,
+        T::RING_TYPE,
+        ring_pointer_free::<T>,
+    );
+}
+
+/// Whether the C pointers in parameters `a` (to an `A`) and `b` (to a `B`)
+/// point into the same memory, such as a value and a view into it. Borrowing
+/// both when one borrow is `&mut` would alias.
+#[doc(hidden)]
+pub fn ring_pointer_params_overlap<A: RingPointer, B: RingPointer>(
+    p: &*mut c_void,
+    a: c_int,
+    b: c_int,
+) -> bool {
+    let range = |n, size| {
+        CPointer::from_param(p, n)
+            .ok()
+            .filter(|pointer| !pointer.ptr.is_null())
+            .map(|pointer| pointer.ptr as usize..pointer.ptr as usize + size)
+    };
+    match (range(a, size_of::<A>()), range(b, size_of::<B>())) {
+        (Some(a), Some(b)) => a.start < b.end && b.start < a.end,
+        _ => false,
+    }
+}
+
+/// Record a view of `field` that keeps `parent` (or its owner) alive; it is
+/// released by one [`ring_pointer_drop`] of `field`.
+pub(crate) fn add_view<P: RingPointer, T: RingPointer>(parent: *mut P, field: *mut T) {
+    let parent = view_key(parent);
+    let mut views = VIEWS.lock().unwrap_or_else(|e| e.into_inner());
+    let owner = views.views.get(&parent).map_or(parent, |(owner, _)| *owner);
+    views.views.entry(view_key(field)).or_insert((owner, 0)).1 += 1;
+    views
+        .owners
+        .entry(owner)
+        .or_insert(ViewOwner {
+            views: 0,
+            deleted: None,
+        })
+        .views += 1;
+}
+
+/// Cloning the boxed value of a struct passed by value, with a clear error
+/// when it isn't `Clone`. Used by `ring_extension!`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is passed by value, so it must implement `Clone`",
+    label = "passed by value here",
+    note = "derive `Clone`, take `&{Self}` or `&mut {Self}`, or use `#[ring(view)]` for a field"
+)]
+pub trait RingByValue: Sized {
+    fn ring_by_value(&self) -> Self;
+}
+
+impl<T: RingPointer + Clone> RingByValue for T {
+    fn ring_by_value(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Read the value of a variable list (see `RING_VAR_*`).
+///
+/// References (`RING_VM_POINTER` variables, e.g. objects passed by reference)
+/// are followed to the value they point to.
+pub fn ring_var_get<T: for<'a> FromRing<'a>>(var: RingList) -> RingResult<T> {
+    with_var_item(var, |item| T::from_item(item))
+}
+
+/// Call `f` with the item a variable list holds. A reference variable's
+/// pointer is wrapped in a temporary pointer item.
+pub(crate) fn with_var_item<R>(var: RingList, f: impl FnOnce(&Item) -> R) -> R {
+    let item = ring_list_getitem(var, RING_VAR_VALUE);
+    if ring_list_getint(var, RING_VAR_TYPE) == RING_VM_POINTER {
+        let objtype = ring_list_getint(var, RING_VAR_PVALUETYPE) as c_uint;
+        let reference = Item {
+            data: ItemData {
+                pPointer: unsafe { (*item).data.pPointer },
+            },
+            flags: ITEMTYPE_POINTER | (objtype << 5),
+            pGCFreeFunc: None,
+        };
+        return f(&reference);
+    }
+    f(unsafe { &*item })
+}
+
+/// Store a value in a variable list (see `RING_VAR_*`), updating its type.
+pub fn ring_var_set(var: RingList, value: impl IntoRing) {
+    let item = ring_list_getitem(var, RING_VAR_VALUE);
+    value.into_item(item);
+    let var_type = match unsafe { (*item).nType() } {
+        ITEMTYPE_NUMBER => RING_VM_NUMBER,
+        ITEMTYPE_LIST => {
+            let list = unsafe { (*item).data.pList };
+            if ring_list_isobject(list) {
+                ring_object_setself(list, var as *mut c_void, RING_OBJTYPE_VARIABLE as c_uint);
+            }
+            RING_VM_LIST
+        }
+        ITEMTYPE_POINTER => RING_VM_POINTER,
+        ITEMTYPE_STRING => RING_VM_STRING,
+        // Variables can't be empty; Ring uses an empty string for "no value"
+        _ => {
+            ring_item_setstring2(item, b"");
+            RING_VM_STRING
+        }
+    };
+    ring_list_setint(var, RING_VAR_TYPE, var_type);
+    ring_list_setint(var, RING_VAR_PVALUETYPE, 0);
+}
+
+/// Follow references so the item holding the actual value is returned.
+///
+/// References to a sub-list have no item of their own and are returned as is;
+/// use [`item_list`] for those.
+pub(crate) fn deref_item(item: &Item) -> &Item {
+    if item.nType() != ITEMTYPE_POINTER {
+        return item;
+    }
+    unsafe {
+        let ptr = item.data.pPointer;
+        if ptr.is_null() {
+            return item;
+        }
+        match item.nObjectType() as c_int {
+            RING_OBJTYPE_VARIABLE => &*ring_list_getitem(ptr as RingList, RING_VAR_VALUE),
+            RING_OBJTYPE_LISTITEM => &*(ptr as RingItem),
+            _ => item,
+        }
+    }
+}
+
+/// The list an item holds or refers to.
+pub(crate) fn item_list(item: &Item) -> Option<RingList> {
+    let item = deref_item(item);
+    unsafe {
+        match item.nType() {
+            ITEMTYPE_LIST => Some(item.data.pList),
+            ITEMTYPE_POINTER
+                if item.nObjectType() as c_int == RING_OBJTYPE_SUBLIST
+                    && !item.data.pPointer.is_null() =>
+            {
+                Some(item.data.pPointer as RingList)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn item_type_name(item: &Item) -> &'static str {
+    if item_list(item).is_some() {
+        return "list";
+    }
+    match deref_item(item).nType() {
+        ITEMTYPE_STRING => "string",
+        ITEMTYPE_NUMBER => "number",
+        ITEMTYPE_POINTER => "pointer",
+        ITEMTYPE_LIST => "list",
+        ffi::ITEMTYPE_FUNCPOINTER => "function pointer",
+        _ => "nothing",
+    }
+}
+
+pub(crate) fn type_error(expected: &str, item: &Item) -> RingError {
+    RingError::new(format!(
+        "expected {expected}, found {}",
+        item_type_name(item)
+    ))
+}
+
+fn item_number(item: &Item) -> RingResult<f64> {
+    let item = deref_item(item);
+    if item.nType() != ITEMTYPE_NUMBER {
+        return Err(type_error("a number", item));
+    }
+    Ok(ring_item_getnumber(item as *const Item as RingItem))
+}
+
+pub(crate) fn item_bytes(item: &Item) -> RingResult<&[u8]> {
+    let item = deref_item(item);
+    if item.nType() != ITEMTYPE_STRING {
+        return Err(type_error("a string", item));
+    }
+    unsafe {
+        let string = item.data.pString;
+        if string.is_null() || (*string).cStr.is_null() || (*string).nSize == 0 {
+            return Ok(&[]);
+        }
+        Ok(std::slice::from_raw_parts(
+            (*string).cStr as *const u8,
+            (*string).nSize as usize,
+        ))
+    }
+}
+
+/// Copy of parameter `n` in an item that lives as long as the call.
+///
+/// The item is added to a list from `ring_api_newlist()`, which the VM frees
+/// once the extension function returns. Lists (and C pointers) are referenced,
+/// not copied.
+pub(crate) fn param_item(p: &*mut c_void, n: c_int) -> RingResult<&Item> {
+    let (p_ref, p) = (p, *p);
+    if n < 1 || n > ring_api_paracount(p) {
+        return Err(RingError::new(format!("missing parameter {n}")));
+    }
+    let item = list_newitem(ring_api_newlist(p));
+    if ring_api_isnumber(p, n) {
+        ring_item_setdouble(item, ring_api_getnumber(p, n));
+    } else if ring_api_isstring(p, n) {
+        ring_item_setstring2(item, ring_api_getstring_bytes_ref(p_ref, n));
+    } else if ring_api_islist(p, n) {
+        unsafe {
+            (*item).data.pPointer = ring_api_getlist(p, n) as *mut c_void;
+            (*item).flags = ITEMTYPE_POINTER | ((RING_OBJTYPE_SUBLIST as c_uint) << 5);
+        }
+    } else {
+        return Err(RingError::new(format!(
+            "parameter {n} has an unsupported type"
+        )));
+    }
+    Ok(unsafe { &*item })
+}
+
+fn param_error(n: c_int, expected: &str) -> RingError {
+    RingError::new(format!(
+        "Bad parameter type! parameter {n} expects {expected}"
+    ))
+}
+
+/// Append an empty item to `list` and return it.
+pub(crate) fn list_newitem(list: RingList) -> RingItem {
+    ring_list_newitem(list);
+    ring_list_getitem(list, ring_list_getsize(list))
+}
+
+/// Accepts any value; used when the result of a call is not needed.
+impl<'a> FromRing<'a> for () {
+    fn from_item(_item: &'a Item) -> RingResult<Self> {
+        Ok(())
+    }
+}
+
+fn param_number(p: *mut c_void, n: c_int) -> RingResult<f64> {
+    if n > ring_api_paracount(p) {
+        return Err(RingError::new(format!("missing parameter {n}")));
+    }
+    if !ring_api_isnumber(p, n) {
+        return Err(param_error(n, "a number"));
+    }
+    Ok(ring_api_getnumber(p, n))
+}
+
+fn param_bytes(p: &*mut c_void, n: c_int) -> RingResult<&[u8]> {
+    if n > ring_api_paracount(*p) {
+        return Err(RingError::new(format!("missing parameter {n}")));
+    }
+    if !ring_api_isstring(*p, n) {
+        return Err(param_error(n, "a string"));
+    }
+    Ok(ring_api_getstring_bytes_ref(p, n))
+}
+
+impl<'a> FromRing<'a> for f64 {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_number(item)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_number(*p, n)
+    }
+}
+
+impl<'a> FromRing<'a> for f32 {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_number(item).map(|n| n as f32)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_number(*p, n).map(|n| n as f32)
+    }
+}
+
+impl<'a> FromRing<'a> for bool {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_number(item).map(|n| n != 0.0)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_number(*p, n).map(|n| n != 0.0)
+    }
+}
+
+/// Numbers are truncated toward zero, like an `as` cast, but values outside
+/// the target range are an error rather than being saturated.
+fn to_integer<T>(n: f64, min: f64, max: f64, name: &str) -> RingResult<T>
+where
+    T: TryFrom<i128>,
+{
+    let n = n.trunc();
+    if n.is_nan() || n < min || n > max {
+        return Err(RingError::new(format!("number {n} does not fit in {name}")));
+    }
+    T::try_from(n as i128).map_err(|_| RingError::new(format!("number {n} does not fit in {name}")))
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {$(
+        impl<'a> FromRing<'a> for $ty {
+            fn from_item(item: &'a Item) -> RingResult<Self> {
+                to_integer(item_number(item)?, <$ty>::MIN as f64, <$ty>::MAX as f64, stringify!($ty))
+            }
+
+            fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+                to_integer(param_number(*p, n)?, <$ty>::MIN as f64, <$ty>::MAX as f64, stringify!($ty))
+            }
+        }
+
+        impl IntoRing for $ty {
+            fn into_item(self, item: RingItem) {
+                ring_item_setdouble(item, self as f64);
+            }
+
+            fn ret(self, p: *mut c_void) {
+                ring_api_retnumber(p, self as f64);
+            }
+        }
+    )*};
+}
+
+impl_integer!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+/// An empty string is no bytes, so byte buffers use an empty list for the
+/// `None` of an `Option`.
+fn is_none_bytes(item: &Item) -> bool {
+    deref_item(item).nType() == ffi::ITEMTYPE_NOTHING
+        || item_list(item).is_some_and(|list| ring_list_getsize(list) == 0)
+}
+
+fn is_none_bytes_param(p: &*mut c_void, n: c_int) -> bool {
+    ring_api_islist(*p, n) && ring_list_getsize(ring_api_getlist(*p, n)) == 0
+}
+
+fn none_bytes_into_item(item: RingItem) {
+    ring_item_settype(item, ITEMTYPE_LIST);
+}
+
+impl<'a> FromRing<'a> for &'a [u8] {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_bytes(item)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_bytes(p, n)
+    }
+
+    fn is_none(item: &Item) -> bool {
+        is_none_bytes(item)
+    }
+
+    fn is_none_param(p: &*mut c_void, n: c_int) -> bool {
+        is_none_bytes_param(p, n)
+    }
+}
+
+/// Bytes of a string. `Vec<u8>` is a list of numbers like any `Vec<T>`; use
+/// [`RingBytes`] for an owned buffer passed as a string.
+impl<'a> FromRing<'a> for Box<[u8]> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_bytes(item).map(Box::from)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_bytes(p, n).map(Box::from)
+    }
+
+    fn is_none(item: &Item) -> bool {
+        is_none_bytes(item)
+    }
+
+    fn is_none_param(p: &*mut c_void, n: c_int) -> bool {
+        is_none_bytes_param(p, n)
+    }
+}
+
+/// Bytes passed as a binary-safe Ring string, the way `ring_extension!` passes
+/// `Vec<u8>`. A plain `Vec<u8>` is a list of numbers like any `Vec<T>`.
+///
+/// An empty string is `Some` of no bytes in an `Option<RingBytes>`, so `None`
+/// is an empty list (as for `&[u8]` and `Box<[u8]>`).
+///
+/// ```rust,ignore
+/// let RingBytes(data) = ring_get!(p, 1, RingBytes);
+/// ring_ret!(p, RingBytes(compress(&data)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RingBytes(pub Vec<u8>);
+
+impl std::ops::Deref for RingBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for RingBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<RingBytes> for Vec<u8> {
+    fn from(bytes: RingBytes) -> Self {
+        bytes.0
+    }
+}
+
+impl<'a> FromRing<'a> for RingBytes {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_bytes(item).map(|bytes| Self(bytes.to_vec()))
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        param_bytes(p, n).map(|bytes| Self(bytes.to_vec()))
+    }
+
+    fn is_none(item: &Item) -> bool {
+        is_none_bytes(item)
+    }
+
+    fn is_none_param(p: &*mut c_void, n: c_int) -> bool {
+        is_none_bytes_param(p, n)
+    }
+}
+
+impl IntoRing for RingBytes {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, &self.0);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, &self.0);
+    }
+
+    fn none_into_item(item: RingItem) {
+        none_bytes_into_item(item);
+    }
+}
+
+fn to_str(bytes: &[u8]) -> RingResult<&str> {
+    std::str::from_utf8(bytes).map_err(|_| RingError::new("string is not valid UTF-8"))
+}
+
+impl<'a> FromRing<'a> for &'a str {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        to_str(item_bytes(item)?)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        to_str(param_bytes(p, n)?)
+    }
+}
+
+impl<'a> FromRing<'a> for String {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        <&str>::from_item(item).map(str::to_owned)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        <&str>::from_param(p, n).map(str::to_owned)
+    }
+}
+
+impl<'a> FromRing<'a> for Cow<'a, str> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        <&str>::from_item(item).map(Cow::Borrowed)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        <&str>::from_param(p, n).map(Cow::Borrowed)
+    }
+}
+
+fn to_char(s: &str) -> RingResult<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(RingError::new(format!(
+            "expected a single character, found {:?}",
+            s
+        ))),
+    }
+}
+
+impl<'a> FromRing<'a> for char {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        to_char(<&str>::from_item(item)?)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        to_char(<&str>::from_param(p, n)?)
+    }
+}
+
+/// Empty strings (Ring's "no value") and empty items read as `None`, or an
+/// empty list for byte buffers. As a parameter, a missing trailing argument is
+/// also `None`.
+impl<'a, T: FromRing<'a>> FromRing<'a> for Option<T> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        if T::is_none(item) {
+            return Ok(None);
+        }
+        T::from_item(item).map(Some)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        if n > ring_api_paracount(*p) || T::is_none_param(p, n) {
+            return Ok(None);
+        }
+        T::from_param(p, n).map(Some)
+    }
+}
+
+impl<'a, T: FromRing<'a>> FromRing<'a> for Box<T> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        T::from_item(item).map(Box::new)
+    }
+
+    fn from_param(p: &'a *mut c_void, n: c_int) -> RingResult<Self> {
+        T::from_param(p, n).map(Box::new)
+    }
+
+    fn is_none(item: &Item) -> bool {
+        T::is_none(item)
+    }
+
+    fn is_none_param(p: &*mut c_void, n: c_int) -> bool {
+        T::is_none_param(p, n)
+    }
+}
+
+/// Read a list of `[key, value]` pairs, such as `[:name = "Ring"]`.
+impl<'a, K, V, S> FromRing<'a> for HashMap<K, V, S>
+where
+    K: FromRing<'a> + Eq + Hash,
+    V: FromRing<'a>,
+    S: BuildHasher + Default,
+{
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        Vec::<(K, V)>::from_item(item).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+/// Read a list of `[key, value]` pairs.
+impl<'a, K: FromRing<'a> + Ord, V: FromRing<'a>> FromRing<'a> for BTreeMap<K, V> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        Vec::<(K, V)>::from_item(item).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+/// Read a list of `[key, value]` pairs, keeping their order.
+#[cfg(feature = "indexmap")]
+impl<'a, K, V, S> FromRing<'a> for IndexMap<K, V, S>
+where
+    K: FromRing<'a> + Eq + Hash,
+    V: FromRing<'a>,
+    S: BuildHasher + Default,
+{
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        Vec::<(K, V)>::from_item(item).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+impl<'a, T: FromRing<'a>> FromRing<'a> for Vec<T> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        let list = item_list(item).ok_or_else(|| type_error("a list", item))?;
+        (1..=ring_list_getsize(list))
+            .map(|i| T::from_item(unsafe { &*ring_list_getitem(list, i) }))
+            .collect()
+    }
+}
+
+impl<'a> FromRing<'a> for CPointer {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        let list = item_list(item)
+            .filter(|&list| ring_list_iscpointerlist(list))
+            .ok_or_else(|| type_error("a C pointer", item))?;
+        let type_item = unsafe { &*ring_list_getitem(list, 2) };
+        Ok(Self {
+            ptr: ring_list_getpointer(list, 1),
+            type_name: String::from_utf8_lossy(item_bytes(type_item)?).into_owned(),
+        })
+    }
+}
+
+impl<'a, T: RingPointer> FromRing<'a> for &'a T {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        pointer_from_item::<T>(item).map(|ptr| unsafe { &*ptr })
+    }
+}
+
+/// The caller must not pass the same object twice; Ring can't enforce
+/// Rust's aliasing rules.
+impl<'a, T: RingPointer> FromRing<'a> for &'a mut T {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        pointer_from_item::<T>(item).map(|ptr| unsafe { &mut *ptr })
+    }
+}
+
+impl IntoRing for f64 {
+    fn into_item(self, item: RingItem) {
+        ring_item_setdouble(item, self);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retnumber(p, self);
+    }
+}
+
+impl IntoRing for f32 {
+    fn into_item(self, item: RingItem) {
+        ring_item_setdouble(item, self as f64);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retnumber(p, self as f64);
+    }
+}
+
+impl IntoRing for bool {
+    fn into_item(self, item: RingItem) {
+        ring_item_setdouble(item, if self { 1.0 } else { 0.0 });
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retnumber(p, if self { 1.0 } else { 0.0 });
+    }
+}
+
+impl IntoRing for &[u8] {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, self);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, self);
+    }
+
+    fn none_into_item(item: RingItem) {
+        none_bytes_into_item(item);
+    }
+}
+
+impl IntoRing for Box<[u8]> {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, &self);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, &self);
+    }
+
+    fn none_into_item(item: RingItem) {
+        none_bytes_into_item(item);
+    }
+}
+
+impl IntoRing for &str {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, self.as_bytes());
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, self.as_bytes());
+    }
+}
+
+impl IntoRing for String {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, self.as_bytes());
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, self.as_bytes());
+    }
+}
+
+impl IntoRing for Cow<'_, str> {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, self.as_bytes());
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, self.as_bytes());
+    }
+}
+
+impl IntoRing for char {
+    fn into_item(self, item: RingItem) {
+        ring_item_setstring2(item, self.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retstring2(p, self.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+}
+
+/// Borrowed values are written as a copy.
+impl<T: IntoRing + Clone> IntoRing for &T {
+    fn into_item(self, item: RingItem) {
+        self.clone().into_item(item);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        self.clone().ret(p);
+    }
+    fn none_into_item(item: RingItem) {
+        T::none_into_item(item);
+    }
+}
+
+/// Writes nothing; extension functions returning `()` return no value.
+impl IntoRing for () {
+    fn into_item(self, item: RingItem) {
+        ring_item_settype(item, ffi::ITEMTYPE_NOTHING);
+    }
+
+    fn ret(self, _p: *mut c_void) {}
+}
+
+/// `None` is written as an empty string, Ring's usual "no value".
+impl<T: IntoRing> IntoRing for Option<T> {
+    fn into_item(self, item: RingItem) {
+        match self {
+            Some(value) => value.into_item(item),
+            None => T::none_into_item(item),
+        }
+    }
+
+    fn ret(self, p: *mut c_void) {
+        match self {
+            Some(value) => value.ret(p),
+            None => {
+                let item = list_newitem(ring_api_newlist(p));
+                T::none_into_item(item);
+                ret_item(p, item);
+            }
+        }
+    }
+}
+
+impl<T: IntoRing> IntoRing for Box<T> {
+    fn into_item(self, item: RingItem) {
+        (*self).into_item(item);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        (*self).ret(p);
+    }
+    fn none_into_item(item: RingItem) {
+        T::none_into_item(item);
+    }
+}
+
+/// Write `[key, value]` pairs, see [`index_string_keys`].
+fn pairs_into_item<K: IntoRing, V: IntoRing>(
+    pairs: impl IntoIterator<Item = (K, V)>,
+    item: RingItem,
+) {
+    ring_item_settype(item, ITEMTYPE_LIST);
+    let list = unsafe { (*item).data.pList };
+    for pair in pairs {
+        pair.into_item(list_newitem(list));
+    }
+    index_string_keys(list);
+}
+
+/// Give a list of `[key, value]` pairs a hash table when every key is a
+/// string, so Ring's `list[:key]` doesn't search it.
+pub(crate) fn index_string_keys(list: RingList) {
+    if is_string_keyed(list) {
+        ring_list_genhashtable2(list);
+    }
+}
+
+/// Whether `list` is a non-empty list of `[key, value]` pairs with string
+/// keys, like Ring's `[:key = value]`.
+pub(crate) fn is_string_keyed(list: RingList) -> bool {
+    let size = ring_list_getsize(list);
+    size > 0
+        && (1..=size).all(|i| {
+            item_list(unsafe { &*ring_list_getitem(list, i) })
+                .filter(|&pair| ring_list_getsize(pair) == 2)
+                .is_some_and(|pair| item_bytes(unsafe { &*ring_list_getitem(pair, 1) }).is_ok())
+        })
+}
+
+/// Written as a list of `[key, value]` pairs, in no particular order.
+impl<K: IntoRing, V: IntoRing, S> IntoRing for HashMap<K, V, S> {
+    fn into_item(self, item: RingItem) {
+        pairs_into_item(self, item);
+    }
+}
+
+/// Written as a list of `[key, value]` pairs, in key order.
+impl<K: IntoRing, V: IntoRing> IntoRing for BTreeMap<K, V> {
+    fn into_item(self, item: RingItem) {
+        pairs_into_item(self, item);
+    }
+}
+
+/// Written as a list of `[key, value]` pairs, in insertion order.
+#[cfg(feature = "indexmap")]
+impl<K: IntoRing, V: IntoRing, S> IntoRing for IndexMap<K, V, S> {
+    fn into_item(self, item: RingItem) {
+        pairs_into_item(self, item);
+    }
+}
+
+impl<T: IntoRing> IntoRing for Vec<T> {
+    fn into_item(self, item: RingItem) {
+        ring_item_settype(item, ITEMTYPE_LIST);
+        let list = unsafe { (*item).data.pList };
+        for value in self {
+            value.into_item(list_newitem(list));
+        }
+    }
+}
+
+impl IntoRing for CPointer {
+    fn into_item(self, item: RingItem) {
+        ring_item_settype(item, ITEMTYPE_LIST);
+        let list = unsafe { (*item).data.pList };
+        ring_list_addpointer(list, self.ptr);
+        ring_list_addstring2(list, self.type_name.as_bytes());
+        ring_list_addint(list, RING_CPOINTERSTATUS_NOTCOPIED);
+    }
+}
+
+macro_rules! impl_tuple {
+    ($(($len:literal: $($name:ident),+)),*) => {$(
+        /// Read a list with exactly as many items as the tuple.
+        impl<'a, $($name: FromRing<'a>),+> FromRing<'a> for ($($name,)+) {
+            fn from_item(item: &'a Item) -> RingResult<Self> {
+                let list = item_list(item).ok_or_else(|| type_error("a list", item))?;
+                if ring_list_getsize(list) != $len {
+                    return Err(RingError::new(format!(
+                        "expected a list of {} items, found {}",
+                        $len,
+                        ring_list_getsize(list)
+                    )));
+                }
+                let mut index = 0;
+                Ok(($({
+                    index += 1;
+                    $name::from_item(unsafe { &*ring_list_getitem(list, index) })?
+                },)+))
+            }
+        }
+
+        impl<$($name: IntoRing),+> IntoRing for ($($name,)+) {
+            fn into_item(self, item: RingItem) {
+                ring_item_settype(item, ITEMTYPE_LIST);
+                let list = unsafe { (*item).data.pList };
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                $($name.into_item(list_newitem(list));)+
+            }
+        }
+    )*};
+}
+
+impl_tuple!(
+    (1: A),
+    (2: A, B),
+    (3: A, B, C),
+    (4: A, B, C, D),
+    (5: A, B, C, D, E),
+    (6: A, B, C, D, E, F),
+    (7: A, B, C, D, E, F, G),
+    (8: A, B, C, D, E, F, G, H)
+);
+
+impl RingArgs for () {
+    fn write_args(self, _next: &mut dyn FnMut() -> RingItem) {}
+}
+
+macro_rules! impl_args {
+    ($(($($name:ident),+)),*) => {$(
+        impl<$($name: IntoRing),+> RingArgs for ($($name,)+) {
+            fn write_args(self, next: &mut dyn FnMut() -> RingItem) {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                $($name.into_item(next());)+
+            }
+        }
+    )*};
+}
+
+impl_args!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H)
+);
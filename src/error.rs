@@ -0,0 +1,50 @@
+//! Error type returned by the safe wrappers.
+
+use std::fmt;
+
+/// A Ring runtime error, or a failure to move a value across the Rust/Ring boundary.
+///
+/// Runtime errors carry the location where the VM raised them, when available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingError {
+    /// The error message, e.g. `Error (R24) : Using uninitialized variable : x`.
+    pub message: String,
+    /// Source file the error was raised in.
+    pub file: Option<String>,
+    /// Line the error was raised on.
+    pub line: Option<u32>,
+    /// Ring function the error was raised in, or `None` at the top level.
+    pub function: Option<String>,
+}
+
+impl RingError {
+    /// Create an error with a message and no location.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            file: None,
+            line: None,
+            function: None,
+        }
+    }
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function {function}()")?;
+        }
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " at {file}:{line}"),
+            (Some(file), None) => write!(f, " in {file}"),
+            (None, Some(line)) => write!(f, " at line {line}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RingError {}
+
+/// Result alias used by the safe wrappers.
+pub type RingResult<T> = Result<T, RingError>;
@@ -0,0 +1,526 @@
+//! Owned Ring interpreter handle.
+//!
+//! [`Ring`] wraps a `RingState` and deletes it when dropped, so embedding code
+//! does not have to pair every `ring_state_new()`/`ring_state_init()` with a
+//! `ring_state_delete()` call by hand.
+//!
+//! [`Ring::eval`] runs code under a `try`/`catch` so that runtime errors come
+//! back as a [`RingError`] instead of terminating the host process. While it
+//! runs, the VM's trace hook records the file, line and function of each new
+//! line and call, because `catch` has already unwound the call stack by the
+//! time the error reaches Rust.
+
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+use std::path::Path;
+
+use crate::convert::{
+    FromRing, IntoRing, RingArgs, list_newitem, param_item, ret_item, ring_var_get, ring_var_set,
+};
+use crate::error::RingError;
+use crate::ffi::{ITEMTYPE_NOTHING, RING_VM_STACK_SIZE, RingVM};
+use crate::ffi_types::{c_int, c_uint, c_void};
+use crate::item::ring_item_setstring2;
+use crate::list::{
+    ring_list_delete, ring_list_getdouble, ring_list_getitem, ring_list_getsize,
+    ring_list_getstring_str, ring_list_new,
+};
+use crate::output::{self, OutputFn};
+use crate::state::{
+    ring_state_delete, ring_state_findvar_str, ring_state_init, ring_state_new,
+    ring_state_newvar_str, ring_state_runcode_str, ring_state_runfile_str,
+    ring_state_runstring_str,
+};
+use crate::value::RingValue;
+use crate::vm::{ring_vm_funcexists, ring_vm_runcode_str};
+use crate::{
+    RingFunc, RingList, RingResult, RingState, ring_api_getnumber, ring_api_getstring_bytes_ref,
+    ring_list_isnumber, ring_list_isstring, ring_register_function, ring_register_function_str,
+};
+
+/// Global holding the code passed to [`Ring::eval`].
+const EVAL_CODE_VAR: &str = "__ring_rs_code";
+
+/// Trace code installed while [`run_catching`] runs; `ringvm_traceevent()` is
+/// passed along so calls into C functions can be told apart from Ring code.
+const TRACE_CODE: &str = "__ring_rs_trace(ringvm_traceevent())";
+
+// Trace events (see `ringvm_traceevent()`) that move to new Ring code.
+const TRACEEVENT_NEWLINE: c_int = 1;
+const TRACEEVENT_NEWFUNC: c_int = 2;
+const TRACEEVENT_RETURN: c_int = 3;
+
+// Items of the VM's trace data list (see `ringvm_tracedata()`).
+const TRACEDATA_LINENUMBER: u32 = 1;
+const TRACEDATA_FILENAME: u32 = 2;
+const TRACEDATA_FUNCNAME: u32 = 3;
+
+/// Where the VM was when the trace hook last ran.
+struct Location {
+    vm: RingVM,
+    file: Option<String>,
+    line: Option<u32>,
+    function: Option<String>,
+}
+
+thread_local! {
+    static CAUGHT_ERROR: RefCell<Option<RingError>> = const { RefCell::new(None) };
+    /// `Some` while [`run_catching`] runs.
+    static TRACED: RefCell<Option<Location>> = const { RefCell::new(None) };
+    static CURRENT_VM: Cell<RingVM> = const { Cell::new(std::ptr::null_mut()) };
+    /// Arguments of the [`call_catching`] calls in progress, innermost last.
+    static CALL_ARGS: RefCell<Vec<RingList>> = const { RefCell::new(Vec::new()) };
+    /// Return value of the last function run by [`call_catching`].
+    static CALL_RESULT: RefCell<Option<RingResult<RingValue>>> = const { RefCell::new(None) };
+}
+
+/// Records the VM pointer, which the state does not expose directly.
+extern "C" fn ring_rs_vm(p: *mut c_void) {
+    CURRENT_VM.with(|vm| vm.set(p as RingVM));
+}
+
+/// The VM of an initialized state (see [`ring_state_vm`](crate::ring_state_vm)).
+///
+/// The recording function is named after its address: each extension links
+/// its own copy of this crate, with its own thread-local.
+pub(crate) fn state_vm(state: RingState) -> RingVM {
+    let name = format!("__ring_rs_vm_{:x}", ring_rs_vm as *const () as usize);
+    ring_register_function_str(state, &format!("{name}\0"), ring_rs_vm);
+    ring_state_runcode_str(state, &format!("{name}()"));
+    CURRENT_VM.with(|vm| vm.replace(std::ptr::null_mut()))
+}
+
+/// Trace hook: records the location of each new line, function call and
+/// return, which is where the VM is when an error is raised.
+extern "C" fn ring_rs_trace(p: *mut c_void) {
+    let event = ring_api_getnumber(p, 1) as c_int;
+    if !matches!(
+        event,
+        TRACEEVENT_NEWLINE | TRACEEVENT_NEWFUNC | TRACEEVENT_RETURN
+    ) {
+        return;
+    }
+    let data = unsafe { (*(p as RingVM)).pTraceData };
+    let size = ring_list_getsize(data);
+    let string = |index| {
+        (size >= index && ring_list_isstring(data, index))
+            .then(|| ring_list_getstring_str(data, index))
+            .filter(|s| !s.is_empty())
+    };
+    let location = Location {
+        vm: p as RingVM,
+        line: (size >= TRACEDATA_LINENUMBER && ring_list_isnumber(data, TRACEDATA_LINENUMBER))
+            .then(|| ring_list_getdouble(data, TRACEDATA_LINENUMBER) as u32)
+            .filter(|&line| line > 0),
+        file: string(TRACEDATA_FILENAME),
+        // Top-level evaluated code runs inside the `eval()` C function.
+        function: string(TRACEDATA_FUNCNAME).filter(|name| name != "eval"),
+    };
+    TRACED.with(|traced| {
+        if let Some(traced) = traced.borrow_mut().as_mut() {
+            if traced.vm == location.vm {
+                *traced = location;
+            }
+        }
+    });
+}
+
+/// Catch handler called by [`run_catching`] with the message from `cCatchError`.
+extern "C" fn ring_rs_catch(p: *mut c_void) {
+    let message = String::from_utf8_lossy(ring_api_getstring_bytes_ref(&p, 1)).into_owned();
+    let mut error = RingError::new(message);
+    TRACED.with(|traced| {
+        if let Some(location) = traced.borrow_mut().take() {
+            error.file = location.file;
+            error.line = location.line;
+            error.function = location.function;
+        }
+    });
+    CAUGHT_ERROR.with(|caught| *caught.borrow_mut() = Some(error));
+}
+
+/// `__ring_rs_callarg(n)`: argument `n` of the innermost [`call_catching`].
+extern "C" fn ring_rs_callarg(p: *mut c_void) {
+    let n = ring_api_getnumber(p, 1) as c_uint;
+    if let Some(args) = CALL_ARGS.with(|args| args.borrow().last().copied()) {
+        if n >= 1 && n <= ring_list_getsize(args) {
+            ret_item(p, ring_list_getitem(args, n));
+        }
+    }
+}
+
+/// `__ring_rs_callret(value)`: the return value of the function called by
+/// [`call_catching`].
+extern "C" fn ring_rs_callret(p: *mut c_void) {
+    let value = param_item(&p, 1).and_then(RingValue::from_item);
+    CALL_RESULT.with(|result| *result.borrow_mut() = Some(value));
+}
+
+/// Register the catch, trace and call handlers with the VM, once.
+fn register_hooks(vm: RingVM) {
+    let state = unsafe { (*vm).pRingState } as RingState;
+    let hooks: [(&str, &[u8], RingFunc); 4] = [
+        ("__ring_rs_catch", b"__ring_rs_catch\0", ring_rs_catch),
+        ("__ring_rs_trace", b"__ring_rs_trace\0", ring_rs_trace),
+        ("__ring_rs_callarg", b"__ring_rs_callarg\0", ring_rs_callarg),
+        ("__ring_rs_callret", b"__ring_rs_callret\0", ring_rs_callret),
+    ];
+    for (name, cname, func) in hooks {
+        if !ring_vm_funcexists(vm, name) {
+            ring_register_function(state, cname, func);
+        }
+    }
+}
+
+/// Run one line of Ring code under `try`/`catch` and return the caught error.
+///
+/// The error carries the location the trace hook recorded last. A run nested
+/// in another one on the same VM (Ring code calling into Rust that runs more
+/// code) keeps the outer trace hook and restores the outer location when it
+/// returns.
+pub(crate) fn run_catching(vm: RingVM, code: &str) -> RingResult<()> {
+    register_hooks(vm);
+    let location = Location {
+        vm,
+        file: None,
+        line: None,
+        function: None,
+    };
+    let outer = TRACED.with(|traced| traced.replace(Some(location)));
+    let wrapper = if outer.as_ref().is_some_and(|outer| outer.vm == vm) {
+        format!("try {code} catch __ring_rs_catch(cCatchError) done")
+    } else {
+        format!(
+            "ringvm_settrace('{TRACE_CODE}') \
+             try {code} catch __ring_rs_catch(cCatchError) done \
+             ringvm_settrace('')"
+        )
+    };
+    CAUGHT_ERROR.with(|caught| caught.borrow_mut().take());
+    ring_vm_runcode_str(vm, &wrapper);
+    TRACED.with(|traced| *traced.borrow_mut() = outer);
+    match CAUGHT_ERROR.with(|caught| caught.borrow_mut().take()) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Stack slots kept free for the `try`/`catch` wrapper around a call.
+const CALL_STACK_RESERVE: usize = 16;
+
+/// Call a Ring function like [`run_catching`] runs code, so a runtime error
+/// in it is returned instead of ending the program.
+///
+/// The arguments are kept in a list the generated call reads back with
+/// `__ring_rs_callarg()`, which returns copies the VM owns, and the result is
+/// copied out by `__ring_rs_callret()`.
+pub(crate) fn call_catching<R, A>(vm: RingVM, name: &str, args: A) -> RingResult<R>
+where
+    R: for<'a> FromRing<'a>,
+    A: RingArgs,
+{
+    let name = name.to_ascii_lowercase();
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || !ring_vm_funcexists(vm, &name)
+    {
+        return Err(RingError::new(format!("function not found: {name}")));
+    }
+    let list = ring_list_new(0);
+    args.write_args(&mut || list_newitem(list));
+    let count = ring_list_getsize(list);
+    let room =
+        RING_VM_STACK_SIZE.saturating_sub(unsafe { (*vm).nSP } as usize + CALL_STACK_RESERVE);
+    if count as usize > room {
+        ring_list_delete(list);
+        return Err(RingError::new(format!(
+            "too many arguments for {name}: {count}, the VM stack has room for {room}"
+        )));
+    }
+    for index in 1..=count {
+        let item = ring_list_getitem(list, index);
+        if unsafe { (*item).nType() } == ITEMTYPE_NOTHING {
+            ring_item_setstring2(item, b"");
+        }
+    }
+    let params: Vec<_> = (1..=count)
+        .map(|n| format!("__ring_rs_callarg({n})"))
+        .collect();
+    let code = format!("__ring_rs_callret({name}({}))", params.join(", "));
+
+    CALL_ARGS.with(|args| args.borrow_mut().push(list));
+    CALL_RESULT.with(|result| result.borrow_mut().take());
+    let outcome = run_catching(vm, &code);
+    CALL_ARGS.with(|args| args.borrow_mut().pop());
+    let value = CALL_RESULT.with(|result| result.borrow_mut().take());
+    ring_list_delete(list);
+    outcome?;
+
+    let value = value.unwrap_or(Ok(RingValue::Nothing))?;
+    let holder = ring_list_new(0);
+    let item = list_newitem(holder);
+    let result = value
+        .to_item(item)
+        .and_then(|()| R::from_item(unsafe { &*item }));
+    ring_list_delete(holder);
+    result
+}
+
+/// An owned Ring interpreter.
+///
+/// The underlying state is deleted on drop. `Ring` holds a raw pointer, so it
+/// is neither `Send` nor `Sync`: a state must stay on the thread that created it.
+/// Use `ring_vm_runcodefromthread()` when Ring code has to run on other threads.
+pub struct Ring {
+    state: RingState,
+    vm: RingVM,
+}
+
+impl Ring {
+    /// Create an interpreter with an initialized VM (`ring_state_init()`).
+    ///
+    /// Use this for [`run_code`](Self::run_code) and [`find_var`](Self::find_var).
+    pub fn new() -> Self {
+        let mut ring = Self::from_state(ring_state_init());
+        ring.vm = state_vm(ring.state);
+        assert!(!ring.vm.is_null(), "failed to initialize Ring VM");
+        register_hooks(ring.vm);
+        ring
+    }
+
+    /// Create an interpreter without a VM (`ring_state_new()`).
+    ///
+    /// Use this for [`run_file`](Self::run_file) and [`run_string`](Self::run_string),
+    /// which compile and run a whole program.
+    pub fn compiler() -> Self {
+        Self::from_state(ring_state_new())
+    }
+
+    fn from_state(state: RingState) -> Self {
+        assert!(!state.is_null(), "failed to create Ring state");
+        Self {
+            state,
+            vm: std::ptr::null_mut(),
+        }
+    }
+
+    /// Raw state pointer, for calling the `ring_state_*` functions directly.
+    ///
+    /// The pointer is owned by this `Ring` and must not be deleted.
+    #[inline]
+    pub fn as_ptr(&self) -> RingState {
+        self.state
+    }
+
+    /// Whether this interpreter was created with an initialized VM.
+    #[inline]
+    pub fn has_vm(&self) -> bool {
+        !self.vm.is_null()
+    }
+
+    /// Raw VM pointer, for calling the `ring_vm_*` functions directly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    #[inline]
+    pub fn vm_ptr(&self) -> RingVM {
+        self.require_vm("vm_ptr");
+        self.vm
+    }
+
+    /// Send what this interpreter's scripts print with `see` and `?` to `sink`
+    /// instead of the terminal.
+    ///
+    /// Each value is written as it is printed. Output of other interpreters,
+    /// and text the VM writes itself (such as uncaught error messages), still
+    /// goes to the terminal. Use [`OutputBuffer`](crate::OutputBuffer) to
+    /// collect it into memory.
+    ///
+    /// This works by defining `ringvm_see()` in the VM, so scripts must not
+    /// define a function of that name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn set_output(&self, sink: impl Write + 'static) {
+        self.require_vm("set_output");
+        output::install(self.vm);
+        output::set_sink(self.vm, Some(Box::new(sink)));
+    }
+
+    /// Pass `see` and `?` output to a callback instead of the terminal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn set_output_fn(&self, callback: impl FnMut(&[u8]) + 'static) {
+        self.set_output(OutputFn(callback));
+    }
+
+    /// Let script output go to the terminal again.
+    pub fn reset_output(&self) {
+        if self.has_vm() {
+            output::set_sink(self.vm, None);
+        }
+    }
+
+    /// Execute code on the initialized VM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn run_code(&self, code: &str) {
+        self.require_vm("run_code");
+        ring_state_runcode_str(self.state, code);
+    }
+
+    /// Execute code on the initialized VM, returning runtime errors instead of
+    /// printing them and exiting.
+    ///
+    /// The code is passed through a variable and run with Ring's `eval()`, so it
+    /// may contain any characters and may define functions and classes.
+    ///
+    /// The error's file, line and function are where the error was raised, e.g.
+    /// inside a function the evaluated code called. Recording them uses the VM's
+    /// trace hook (`ringvm_settrace()`), which slows the evaluated code down, and
+    /// replaces any trace the script installs itself until `eval` returns.
+    ///
+    /// ```rust,ignore
+    /// let err = ring.eval("func check n if n < 0 raise('negative') ok\ncheck(-1)").unwrap_err();
+    /// assert_eq!(err.function.as_deref(), Some("check"));
+    /// assert_eq!(err.line, Some(1));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn eval(&self, code: &str) -> Result<(), RingError> {
+        self.require_vm("eval");
+        self.set_global(EVAL_CODE_VAR, code);
+        let result = run_catching(self.vm, "eval(__ring_rs_code)");
+        self.set_global(EVAL_CODE_VAR, "");
+        result
+    }
+
+    /// Read a Ring source file and [`eval`](Self::eval) it on the initialized VM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn eval_file(&self, path: impl AsRef<Path>) -> Result<(), RingError> {
+        let path = path.as_ref();
+        let code = std::fs::read_to_string(path).map_err(|e| {
+            let mut error = RingError::new(format!("Can't read file: {e}"));
+            error.file = Some(path.display().to_string());
+            error
+        })?;
+        self.eval(&code)
+    }
+
+    /// Compile and run a Ring source file. Returns `false` if Ring reports a failure.
+    pub fn run_file(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref().to_string_lossy();
+        ring_state_runfile_str(self.state, &path) != 0
+    }
+
+    /// Compile and run a string as a complete program. Returns `false` if Ring reports a failure.
+    pub fn run_string(&self, code: &str) -> bool {
+        ring_state_runstring_str(self.state, code) != 0
+    }
+
+    /// Find a global variable by name.
+    ///
+    /// Returns the variable list (see `RING_VAR_NAME`, `RING_VAR_TYPE`, `RING_VAR_VALUE`),
+    /// or `None` if the variable does not exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn find_var(&self, name: &str) -> Option<RingList> {
+        self.require_vm("find_var");
+        let var = ring_state_findvar_str(self.state, name);
+        if var.is_null() { None } else { Some(var) }
+    }
+
+    /// Call a Ring function, or a C function registered with the VM, and
+    /// convert its return value.
+    ///
+    /// `args` is `()` or a tuple of values implementing [`IntoRing`]. Names are
+    /// case-insensitive, as in Ring. A runtime error in the function, or calling
+    /// one that does not exist, returns an error. Lists are passed as copies.
+    ///
+    /// ```rust,ignore
+    /// ring.run_code("func add a, b return a + b");
+    /// let sum: f64 = ring.call("add", (1, 2))?;
+    /// let len: usize = ring.call("len", (vec!["a", "b", "c"],))?;
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn call<R, A>(&self, name: &str, args: A) -> Result<R, RingError>
+    where
+        R: for<'a> FromRing<'a>,
+        A: RingArgs,
+    {
+        self.require_vm("call");
+        call_catching(self.vm, name, args)
+    }
+
+    /// Read a global variable, converting it to `T`.
+    ///
+    /// Fails if the variable does not exist or holds a value of another type.
+    ///
+    /// ```rust,ignore
+    /// ring.run_code("total = 6 * 7  names = ['a', 'b']");
+    /// let total: f64 = ring.get_global("total")?;
+    /// let names: Vec<String> = ring.get_global("names")?;
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn get_global<T: for<'a> FromRing<'a>>(&self, name: &str) -> Result<T, RingError> {
+        let var = self
+            .find_var(&name.to_ascii_lowercase())
+            .ok_or_else(|| RingError::new(format!("variable not found: {name}")))?;
+        ring_var_get(var).map_err(|e| RingError::new(format!("variable {name}: {}", e.message)))
+    }
+
+    /// Set a global variable, creating it if needed.
+    ///
+    /// The value is stored directly in the variable, so strings need no quoting
+    /// or escaping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interpreter was created with [`Ring::compiler`].
+    pub fn set_global(&self, name: &str, value: impl IntoRing) {
+        let name = name.to_ascii_lowercase();
+        let var = match self.find_var(&name) {
+            Some(var) => var,
+            None => ring_state_newvar_str(self.state, &name),
+        };
+        ring_var_set(var, value);
+    }
+
+    fn require_vm(&self, method: &str) {
+        assert!(
+            self.has_vm(),
+            "Ring::{method} requires an initialized VM; create the interpreter with Ring::new()"
+        );
+    }
+}
+
+impl Default for Ring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        self.reset_output();
+        ring_state_delete(self.state);
+    }
+}
@@ -18,7 +18,8 @@
 //! - Pointers (`RingState`, `RingList`, `RingVM`) are valid and non-null
 //! - Objects are not used after being deleted
 //! - `ring_list_getstring_str()` returns an owned `String` (safe to store)
-//! - `ring_api_getstring_str()` returns a reference valid only during the callback
+//! - `ring_api_getstring_str()` returns a reference valid only during the callback;
+//!   `ring_api_getstring_str_ref()` borrows `p`, so the string can't outlive it
 
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
@@ -29,25 +30,46 @@
 #![allow(clippy::manual_c_str_literals)]
 
 pub mod api;
+pub mod call;
+pub mod callback;
+pub mod convert;
+pub mod error;
 pub mod ffi;
 mod ffi_types;
 pub mod general;
+pub mod interpreter;
 pub mod item;
 pub mod list;
+pub mod list_ref;
+pub mod output;
+#[cfg(feature = "serde")]
+pub mod serde_bridge;
 pub mod state;
 pub mod string;
 #[cfg(test)]
 mod tests;
+pub mod value;
 pub mod vm;
 #[macro_use]
 pub mod macros;
 
 pub use api::*;
+pub use call::*;
+pub use callback::*;
+pub use convert::*;
+pub use error::*;
+pub use ffi::{Item, RingItem};
 pub use general::*;
+pub use interpreter::*;
 pub use item::*;
 pub use list::*;
+pub use list_ref::*;
+pub use output::{OutputBuffer, OutputFn};
+#[cfg(feature = "serde")]
+pub use serde_bridge::*;
 pub use state::*;
 pub use string::*;
+pub use value::*;
 pub use vm::*;
 
 use ffi_types::c_void;
@@ -57,7 +79,11 @@ pub type RingVM = *mut ffi::VM;
 pub type RingList = *mut ffi::List;
 pub type RingFunc = extern "C" fn(*mut c_void);
 
+pub const RING_CPOINTER_POINTER: ffi_types::c_uint = 1;
+pub const RING_CPOINTER_TYPE: ffi_types::c_uint = 2;
 pub const RING_CPOINTER_STATUS: ffi_types::c_uint = 3;
+pub const RING_CPOINTERSTATUS_NOTCOPIED: ffi_types::c_int = 0;
+pub const RING_CPOINTERSTATUS_COPIED: ffi_types::c_int = 1;
 pub const RING_CPOINTERSTATUS_NOTASSIGNED: ffi_types::c_int = 2;
 
 pub const RING_OUTPUT_RETLIST: ffi_types::c_int = 0;
@@ -73,6 +99,16 @@ pub const RING_VAR_VALUE: ffi_types::c_uint = 3;
 pub const RING_VAR_PVALUETYPE: ffi_types::c_uint = 4;
 pub const RING_VAR_PRIVATEFLAG: ffi_types::c_uint = 5;
 
+pub const RING_VM_NULL: ffi_types::c_int = 0;
+pub const RING_VM_STRING: ffi_types::c_int = 1;
+pub const RING_VM_NUMBER: ffi_types::c_int = 2;
+pub const RING_VM_LIST: ffi_types::c_int = 3;
+pub const RING_VM_POINTER: ffi_types::c_int = 4;
+
+pub const RING_OBJTYPE_VARIABLE: ffi_types::c_int = 1;
+pub const RING_OBJTYPE_LISTITEM: ffi_types::c_int = 2;
+pub const RING_OBJTYPE_SUBLIST: ffi_types::c_int = 3;
+
 pub const RING_API_MISS1PARA: &[u8] = b"Bad parameters count, the function expect one parameter\0";
 pub const RING_API_MISS2PARA: &[u8] = b"Bad parameters count, the function expect two parameters\0";
 pub const RING_API_MISS3PARA: &[u8] =
@@ -0,0 +1,422 @@
+//! Borrowed, bounds-checked views of Ring lists.
+//!
+//! [`ListRef`] and [`ListMut`] wrap a [`RingList`] for the lifetime of the
+//! borrow they come from and check every index before calling into Ring.
+//! Indexes are 0-based like Rust slices; the `ring_list_*` functions they are
+//! built on use Ring's 1-based indexes.
+//!
+//! ```rust,ignore
+//! ring_func!(ring_sum, |p| {
+//!     ring_check_paracount!(p, 1);
+//!     let list = ring_get!(p, 1, ListRef);
+//!     let sum: f64 = list.iter().filter_map(|item| item.as_number().ok()).sum();
+//!     ring_ret!(p, sum);
+//! });
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::api::ring_api_retlist;
+use crate::convert::{
+    FromRing, IntoRing, deref_item, item_list, item_type_name, list_newitem, type_error,
+};
+use crate::ffi::{self, ITEMTYPE_LIST, Item, RingItem};
+use crate::ffi_types::{c_uint, c_void};
+use crate::item::ring_item_settype;
+use crate::list::{
+    ring_list_addcpointer, ring_list_adddouble, ring_list_addpointer, ring_list_addstring2,
+    ring_list_copy, ring_list_deleteallitems, ring_list_deleteitem, ring_list_getitem,
+    ring_list_getsize, ring_list_insertdouble, ring_list_insertitem, ring_list_insertlist,
+    ring_list_insertpointer, ring_list_insertstring2, ring_list_newlist,
+};
+use crate::{RingError, RingList, RingResult};
+
+/// A shared view of a Ring list.
+#[derive(Clone, Copy)]
+pub struct ListRef<'a> {
+    list: RingList,
+    _marker: PhantomData<&'a ffi::List>,
+}
+
+/// A mutable view of a Ring list.
+pub struct ListMut<'a> {
+    list: RingList,
+    _marker: PhantomData<&'a mut ffi::List>,
+}
+
+/// A borrowed list item.
+#[derive(Clone, Copy)]
+pub struct ItemRef<'a> {
+    item: &'a Item,
+}
+
+/// Iterator over the items of a [`ListRef`].
+pub struct ListIter<'a> {
+    list: RingList,
+    next: c_uint,
+    end: c_uint,
+    _marker: PhantomData<&'a ffi::List>,
+}
+
+fn index_error(index: usize, len: usize) -> RingError {
+    RingError::new(format!(
+        "index {index} out of range for list of length {len}"
+    ))
+}
+
+impl<'a> ListRef<'a> {
+    /// Wrap a raw list.
+    ///
+    /// # Safety
+    ///
+    /// `list` must be a valid list that is neither freed nor modified
+    /// elsewhere while the view is alive.
+    pub unsafe fn from_raw(list: RingList) -> Self {
+        Self {
+            list,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_ptr(&self) -> RingList {
+        self.list
+    }
+
+    pub fn len(&self) -> usize {
+        ring_list_getsize(self.list) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The item at `index`, or `None` if it is out of range.
+    pub fn get(&self, index: usize) -> Option<ItemRef<'a>> {
+        if index >= self.len() {
+            return None;
+        }
+        let item = ring_list_getitem(self.list, index as c_uint + 1);
+        Some(ItemRef {
+            item: unsafe { &*item },
+        })
+    }
+
+    /// The item at `index` converted to `T`.
+    pub fn get_as<T: FromRing<'a>>(&self, index: usize) -> RingResult<T> {
+        self.item(index)?.get()
+    }
+
+    pub fn get_number(&self, index: usize) -> RingResult<f64> {
+        self.item(index)?.as_number()
+    }
+
+    pub fn get_str(&self, index: usize) -> RingResult<&'a str> {
+        self.item(index)?.as_str()
+    }
+
+    pub fn get_bytes(&self, index: usize) -> RingResult<&'a [u8]> {
+        self.item(index)?.as_bytes()
+    }
+
+    pub fn get_list(&self, index: usize) -> RingResult<ListRef<'a>> {
+        self.item(index)?.as_list()
+    }
+
+    pub fn get_pointer(&self, index: usize) -> RingResult<*mut c_void> {
+        self.item(index)?.as_pointer()
+    }
+
+    pub fn iter(&self) -> ListIter<'a> {
+        ListIter {
+            list: self.list,
+            next: 1,
+            end: ring_list_getsize(self.list),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Copy the list into a `Vec` of `T`.
+    pub fn to_vec<T: FromRing<'a>>(&self) -> RingResult<Vec<T>> {
+        self.iter().map(|item| item.get()).collect()
+    }
+
+    fn item(&self, index: usize) -> RingResult<ItemRef<'a>> {
+        self.get(index)
+            .ok_or_else(|| index_error(index, self.len()))
+    }
+}
+
+impl<'a> IntoIterator for ListRef<'a> {
+    type Item = ItemRef<'a>;
+    type IntoIter = ListIter<'a>;
+
+    fn into_iter(self) -> ListIter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> Iterator for ListIter<'a> {
+    type Item = ItemRef<'a>;
+
+    fn next(&mut self) -> Option<ItemRef<'a>> {
+        if self.next > self.end {
+            return None;
+        }
+        let item = ring_list_getitem(self.list, self.next);
+        self.next += 1;
+        Some(ItemRef {
+            item: unsafe { &*item },
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.end + 1 - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ListIter<'_> {}
+
+impl<'a> ListMut<'a> {
+    /// Wrap a raw list.
+    ///
+    /// # Safety
+    ///
+    /// `list` must be a valid list that is neither freed nor accessed
+    /// elsewhere while the view is alive.
+    pub unsafe fn from_raw(list: RingList) -> Self {
+        Self {
+            list,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Turn `item` into an empty list and wrap it, for [`IntoRing`] impls.
+    /// Like the `ring_item_*` functions, `item` must be valid.
+    pub fn new_in_item(item: RingItem) -> Self {
+        ring_item_settype(item, ITEMTYPE_LIST);
+        unsafe { Self::from_raw((*item).data.pList) }
+    }
+
+    pub fn as_ptr(&self) -> RingList {
+        self.list
+    }
+
+    /// A shared view, borrowed from this one.
+    pub fn as_list_ref(&self) -> ListRef<'_> {
+        unsafe { ListRef::from_raw(self.list) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_list_ref().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<ItemRef<'_>> {
+        self.as_list_ref().get(index)
+    }
+
+    pub fn iter(&self) -> ListIter<'_> {
+        self.as_list_ref().iter()
+    }
+
+    /// The sublist at `index`, for modification.
+    pub fn get_list_mut(&mut self, index: usize) -> RingResult<ListMut<'_>> {
+        let list = self.as_list_ref().get_list(index)?.as_ptr();
+        Ok(unsafe { ListMut::from_raw(list) })
+    }
+
+    /// Append a value.
+    pub fn push<T: IntoRing>(&mut self, value: T) {
+        value.into_item(list_newitem(self.list));
+    }
+
+    pub fn push_number(&mut self, n: f64) {
+        ring_list_adddouble(self.list, n);
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        ring_list_addstring2(self.list, s.as_bytes());
+    }
+
+    pub fn push_bytes(&mut self, s: &[u8]) {
+        ring_list_addstring2(self.list, s);
+    }
+
+    pub fn push_pointer(&mut self, ptr: *mut c_void) {
+        ring_list_addpointer(self.list, ptr);
+    }
+
+    /// Append a C pointer list; `ctype` is a NUL-terminated type name.
+    pub fn push_cpointer(&mut self, ptr: *mut c_void, ctype: &[u8]) {
+        ring_list_addcpointer(self.list, ptr, ctype);
+    }
+
+    /// Append an empty sublist and return it.
+    pub fn push_list(&mut self) -> ListMut<'_> {
+        unsafe { ListMut::from_raw(ring_list_newlist(self.list)) }
+    }
+
+    /// Insert `value` so that it ends up at `index`; `index == len()` appends.
+    pub fn insert<T: IntoRing>(&mut self, index: usize, value: T) -> RingResult<()> {
+        let pos = self.insert_pos(index)?;
+        ring_list_insertitem(self.list, pos);
+        value.into_item(ring_list_getitem(self.list, pos + 1));
+        Ok(())
+    }
+
+    pub fn insert_number(&mut self, index: usize, n: f64) -> RingResult<()> {
+        let pos = self.insert_pos(index)?;
+        ring_list_insertdouble(self.list, pos, n);
+        Ok(())
+    }
+
+    pub fn insert_str(&mut self, index: usize, s: &str) -> RingResult<()> {
+        self.insert_bytes(index, s.as_bytes())
+    }
+
+    pub fn insert_bytes(&mut self, index: usize, s: &[u8]) -> RingResult<()> {
+        let pos = self.insert_pos(index)?;
+        ring_list_insertstring2(self.list, pos, s);
+        Ok(())
+    }
+
+    pub fn insert_pointer(&mut self, index: usize, ptr: *mut c_void) -> RingResult<()> {
+        let pos = self.insert_pos(index)?;
+        ring_list_insertpointer(self.list, pos, ptr);
+        Ok(())
+    }
+
+    /// Insert an empty sublist at `index` and return it.
+    pub fn insert_list(&mut self, index: usize) -> RingResult<ListMut<'_>> {
+        let pos = self.insert_pos(index)?;
+        Ok(unsafe { ListMut::from_raw(ring_list_insertlist(self.list, pos)) })
+    }
+
+    /// Replace the item at `index`.
+    pub fn set<T: IntoRing>(&mut self, index: usize, value: T) -> RingResult<()> {
+        let len = self.len();
+        if index >= len {
+            return Err(index_error(index, len));
+        }
+        value.into_item(ring_list_getitem(self.list, index as c_uint + 1));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> RingResult<()> {
+        let len = self.len();
+        if index >= len {
+            return Err(index_error(index, len));
+        }
+        ring_list_deleteitem(self.list, index as c_uint + 1);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        ring_list_deleteallitems(self.list);
+    }
+
+    /// Ring inserts after the given 1-based position, so a 0-based `index`
+    /// maps to position `index`.
+    fn insert_pos(&self, index: usize) -> RingResult<c_uint> {
+        let len = self.len();
+        if index > len {
+            return Err(index_error(index, len));
+        }
+        Ok(index as c_uint)
+    }
+}
+
+impl<'a> ItemRef<'a> {
+    pub fn as_item(&self) -> &'a Item {
+        self.item
+    }
+
+    /// The `ITEMTYPE_*` of the value, after following references.
+    pub fn item_type(&self) -> c_uint {
+        deref_item(self.item).nType()
+    }
+
+    /// Ring's name for the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        item_type_name(self.item)
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.item_type() == ffi::ITEMTYPE_NUMBER
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.item_type() == ffi::ITEMTYPE_STRING
+    }
+
+    pub fn is_list(&self) -> bool {
+        item_list(self.item).is_some()
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        self.item_type() == ffi::ITEMTYPE_POINTER && !self.is_list()
+    }
+
+    /// Convert the value to `T`.
+    pub fn get<T: FromRing<'a>>(&self) -> RingResult<T> {
+        T::from_item(self.item)
+    }
+
+    pub fn as_number(&self) -> RingResult<f64> {
+        self.get()
+    }
+
+    pub fn as_str(&self) -> RingResult<&'a str> {
+        self.get()
+    }
+
+    pub fn as_bytes(&self) -> RingResult<&'a [u8]> {
+        self.get()
+    }
+
+    pub fn as_list(&self) -> RingResult<ListRef<'a>> {
+        self.get()
+    }
+
+    pub fn as_pointer(&self) -> RingResult<*mut c_void> {
+        if !self.is_pointer() {
+            return Err(type_error("a pointer", self.item));
+        }
+        Ok(unsafe { deref_item(self.item).data.pPointer })
+    }
+}
+
+impl<'a> FromRing<'a> for ListRef<'a> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        item_list(item)
+            .map(|list| unsafe { ListRef::from_raw(list) })
+            .ok_or_else(|| type_error("a list", item))
+    }
+}
+
+/// Writes a copy of the list.
+impl IntoRing for ListRef<'_> {
+    fn into_item(self, item: RingItem) {
+        ring_item_settype(item, ITEMTYPE_LIST);
+        ring_list_copy(unsafe { (*item).data.pList }, self.list);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retlist(p, self.list);
+    }
+}
+
+/// Writes a copy of the list.
+impl IntoRing for ListMut<'_> {
+    fn into_item(self, item: RingItem) {
+        self.as_list_ref().into_item(item);
+    }
+
+    fn ret(self, p: *mut c_void) {
+        ring_api_retlist(p, self.list);
+    }
+}
@@ -1,35 +1,69 @@
+/// Define an exported Ring function. A panic in the body is raised as a Ring
+/// error rather than unwinding into C (see [`ring_func_guard`](crate::ring_func_guard)).
+///
+/// The body takes either the raw `p` pointer or, when its parameter is typed
+/// as `RingCall`, a [`RingCall`](crate::RingCall) context.
 #[macro_export]
 macro_rules! ring_func {
+    ($name:ident, |$call:ident : RingCall $(<$lt:lifetime>)?| $body:expr) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn $name(p: *mut ::std::ffi::c_void) {
+            $crate::ring_func_guard(p, |p| {
+                let $call = unsafe { $crate::RingCall::from_raw(p) };
+                $body
+            })
+        }
+    };
     ($name:ident, $body:expr) => {
         #[unsafe(no_mangle)]
         pub extern "C" fn $name(p: *mut ::std::ffi::c_void) {
-            $body(p)
+            $crate::ring_func_guard(p, $body)
         }
     };
 }
 
+/// Define `ringlib_init`, which Ring calls when the library is loaded.
+///
+/// `"name" => func` registers a function and `"name" = value` sets a global
+/// Ring variable with [`ring_vm_setglobal`](crate::ring_vm_setglobal), looking
+/// up the VM once. Entries can be grouped under attributes:
+/// `#[cfg(unix)] { "name" => func }`.
+///
+/// ```rust,ignore
+/// ring_libinit! {
+///     "mylib_add" => ring_add,
+///     "mylib_version" = env!("CARGO_PKG_VERSION"),
+/// }
+/// ```
 #[macro_export]
 macro_rules! ring_libinit {
-    (@cfg $state:ident [ ] { $($name:literal => $func:ident),* $(,)? }) => {
-        $( $crate::ring_register_function_str($state, concat!($name, "\0"), $func); )*
+    (@cfg $state:ident $vm:ident [ ] { $($body:tt)* }) => {
+        $crate::ring_libinit!(@munch $state $vm $($body)*);
     };
-    (@cfg $state:ident [ #[$attr:meta] $($rest:tt)* ] { $($body:tt)* }) => {
+    (@cfg $state:ident $vm:ident [ #[$attr:meta] $($rest:tt)* ] { $($body:tt)* }) => {
         #[$attr]
-        { $crate::ring_libinit!(@cfg $state [ $($rest)* ] { $($body)* }); }
+        { $crate::ring_libinit!(@cfg $state $vm [ $($rest)* ] { $($body)* }); }
     };
-    (@munch $state:ident) => {};
-    (@munch $state:ident $(#[$attr:meta])+ { $($name:literal => $func:ident),* $(,)? } $(, $($rest:tt)*)?) => {
-        $crate::ring_libinit!(@cfg $state [ $(#[$attr])* ] { $($name => $func),* });
-        $( $crate::ring_libinit!(@munch $state $($rest)*); )?
+    (@munch $state:ident $vm:ident) => {};
+    (@munch $state:ident $vm:ident $(#[$attr:meta])+ { $($body:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::ring_libinit!(@cfg $state $vm [ $(#[$attr])* ] { $($body)* });
+        $( $crate::ring_libinit!(@munch $state $vm $($rest)*); )?
     };
-    (@munch $state:ident $name:literal => $func:ident $(, $($rest:tt)*)?) => {
+    (@munch $state:ident $vm:ident $name:literal => $func:ident $(, $($rest:tt)*)?) => {
         $crate::ring_register_function_str($state, concat!($name, "\0"), $func);
-        $( $crate::ring_libinit!(@munch $state $($rest)*); )?
+        $( $crate::ring_libinit!(@munch $state $vm $($rest)*); )?
+    };
+    (@munch $state:ident $vm:ident $name:literal = $value:expr $(, $($rest:tt)*)?) => {
+        let vm = *$vm.get_or_init(|| $crate::ring_state_vm($state));
+        $crate::ring_vm_setglobal(vm, $name, $value);
+        $( $crate::ring_libinit!(@munch $state $vm $($rest)*); )?
     };
     ($($tt:tt)*) => {
         #[unsafe(no_mangle)]
         pub extern "C" fn ringlib_init(state: $crate::RingState) {
-            $crate::ring_libinit!(@munch state $($tt)*);
+            // Looked up by the first variable, if any.
+            let __vm = ::std::cell::OnceCell::<$crate::RingVM>::new();
+            $crate::ring_libinit!(@munch state __vm $($tt)*);
         }
     };
 }
@@ -53,8 +87,7 @@ macro_rules! ring_check_paracount {
 #[macro_export]
 macro_rules! ring_check_paracount_range {
     ($p:expr, $min:expr, $max:expr) => {
-        let count = $crate::ring_api_paracount($p);
-        if count < $min || count > $max {
+        if !($min..=$max).contains(&$crate::ring_api_paracount($p)) {
             $crate::ring_api_error($p, $crate::RING_API_BADPARACOUNT);
             return;
         }
@@ -114,7 +147,7 @@ macro_rules! ring_check_list {
 #[macro_export]
 macro_rules! ring_get_string {
     ($p:expr, $n:expr) => {
-        $crate::ring_api_getstring_str($p, $n)
+        $crate::ring_api_getstring_str_ref(&$p, $n)
     };
 }
 
@@ -158,6 +191,118 @@ macro_rules! ring_get_list {
     };
 }
 
+/// Read parameter `n` as any [`FromRing`](crate::FromRing) type. On a type
+/// mismatch the Ring error is raised and the function returns.
+///
+/// `p` must be a variable: borrowed values such as `&str` and `ListRef` borrow
+/// it, so they can't be kept past the call.
+#[macro_export]
+macro_rules! ring_get {
+    ($p:expr, $n:expr, $type:ty) => {
+        match <$type as $crate::FromRing>::from_param(&$p, $n) {
+            Ok(value) => value,
+            Err(err) => {
+                $crate::ring_api_error_str($p, &err.message);
+                return;
+            }
+        }
+    };
+}
+
+/// Make a type passable to Ring as a typed C pointer (see
+/// [`RingPointer`](crate::RingPointer)). The Ring type name defaults to the
+/// type's name.
+///
+/// ```rust,ignore
+/// ring_pointer_type!(Counter);
+///
+/// ring_func!(ring_counter_add, |p| {
+///     ring_check_paracount!(p, 2);
+///     let counter = ring_get!(p, 1, &mut Counter);
+///     counter.value += ring_get!(p, 2, i64);
+/// });
+/// ```
+#[macro_export]
+macro_rules! ring_pointer_type {
+    (@impl $ty:ty, $name:expr) => {
+        impl $crate::RingPointer for $ty {
+            const RING_TYPE: &'static [u8] = concat!($name, "\0").as_bytes();
+        }
+
+        /// A clone of the value the pointer refers to.
+        impl<'a> $crate::FromRing<'a> for $ty
+        where
+            for<'x> $ty: Clone,
+        {
+            fn from_item(item: &'a $crate::Item) -> $crate::RingResult<Self> {
+                <&$ty as $crate::FromRing>::from_item(item).map(|value| value.clone())
+            }
+        }
+
+        /// Boxed and passed to Ring as a C pointer.
+        impl $crate::IntoRing for $ty {
+            fn into_item(self, item: $crate::RingItem) {
+                $crate::ring_pointer_into_item(self, item);
+            }
+
+            fn ret(self, p: *mut ::std::ffi::c_void) {
+                $crate::ring_pointer_ret(self, p);
+            }
+
+            fn none_into_item(item: $crate::RingItem) {
+                $crate::ring_pointer_none_into_item::<$ty>(item);
+            }
+        }
+    };
+    ($ty:ident) => {
+        $crate::ring_pointer_type!(@impl $ty, stringify!($ty));
+    };
+    ($ty:ty, $name:literal) => {
+        $crate::ring_pointer_type!(@impl $ty, $name);
+    };
+}
+
+/// Accept a C pointer to any of the listed [`ring_pointer_type!`] types as a
+/// trait object, choosing the type by the pointer's type name.
+///
+/// ```rust,ignore
+/// ring_pointer_dyn!(Storage: MemStore, FileStore);
+///
+/// ring_func!(ring_storage_get, |p| {
+///     ring_check_paracount!(p, 2);
+///     let store = ring_get!(p, 1, &dyn Storage);
+///     ring_ret!(p, store.get(ring_get!(p, 2, &str)));
+/// });
+/// ```
+#[macro_export]
+macro_rules! ring_pointer_dyn {
+    ($tr:path: $($ty:ty),+ $(,)?) => {
+        impl<'a> $crate::FromRing<'a> for &'a (dyn $tr + 'a) {
+            fn from_item(item: &'a $crate::Item) -> $crate::RingResult<Self> {
+                $(
+                    if $crate::ring_pointer_is::<$ty>(item) {
+                        return <&$ty as $crate::FromRing>::from_item(item).map(|value| value as _);
+                    }
+                )+
+                Err($crate::ring_pointer_dyn_error(item, stringify!($tr)))
+            }
+        }
+
+        /// The caller must not pass the same object twice; Ring can't enforce
+        /// Rust's aliasing rules.
+        impl<'a> $crate::FromRing<'a> for &'a mut (dyn $tr + 'a) {
+            fn from_item(item: &'a $crate::Item) -> $crate::RingResult<Self> {
+                $(
+                    if $crate::ring_pointer_is::<$ty>(item) {
+                        return <&mut $ty as $crate::FromRing>::from_item(item).map(|value| value as _);
+                    }
+                )+
+                Err($crate::ring_pointer_dyn_error(item, stringify!($tr)))
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! ring_ret_number {
     ($p:expr, $n:expr) => {
@@ -198,6 +343,14 @@ macro_rules! ring_ret_list {
     };
 }
 
+/// Return any [`IntoRing`](crate::IntoRing) value.
+#[macro_export]
+macro_rules! ring_ret {
+    ($p:expr, $value:expr) => {
+        $crate::IntoRing::ret($value, $p)
+    };
+}
+
 #[macro_export]
 macro_rules! ring_new_list {
     ($p:expr) => {
@@ -0,0 +1,218 @@
+//! Capturing script output.
+//!
+//! Ring lets a program replace the `see` command (and `?`) by defining a
+//! `ringvm_see()` function. [`Ring::set_output`](crate::Ring::set_output)
+//! defines one in the interpreter's VM that hands each value to the sink
+//! registered for that VM as it is printed, and falls back to Ring's own
+//! `ring_see()` when there is none. Other interpreters, the host process and
+//! C code printing straight to stdout (such as VM error messages) are not
+//! affected.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::api::{ring_api_retnumber, ring_func_guard};
+use crate::convert::{deref_item, item_bytes, item_list, param_item};
+use crate::ffi::{self, ITEMTYPE_NUMBER, ITEMTYPE_POINTER, ITEMTYPE_STRING, Item, RingVM};
+use crate::ffi_types::{CStr, c_char, c_void};
+use crate::item::ring_item_getnumber;
+use crate::list::{
+    ring_list_getitem, ring_list_getlist, ring_list_getsize, ring_list_getstring_str,
+    ring_list_isobject,
+};
+use crate::state::ring_state_runcode;
+use crate::vm::ring_vm_funcexists;
+use crate::{RING_VAR_NAME, RING_VAR_VALUE, RingState, ring_register_function};
+
+/// Replaces `see`; `__ring_rs_see()` returns false when there is no sink.
+const SEE_HOOK: &[u8] = b"func ringvm_see value if not __ring_rs_see(value) ring_see(value) ok\0";
+
+/// Index of the attribute variables in an object list.
+const OBJECT_DATA: u32 = 2;
+
+/// Nested lists deeper than this are not printed (lists can contain themselves).
+const MAX_DEPTH: usize = 64;
+
+type Sink = Rc<RefCell<Box<dyn Write>>>;
+
+thread_local! {
+    /// Sinks by VM. `Ring` is neither `Send` nor `Sync`, so a VM's sink is
+    /// always on the thread running it.
+    static SINKS: RefCell<Vec<(RingVM, Sink)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Define `ringvm_see()` in `vm`, once.
+pub(crate) fn install(vm: RingVM) {
+    if ring_vm_funcexists(vm, "__ring_rs_see") {
+        return;
+    }
+    let state = unsafe { (*vm).pRingState } as RingState;
+    ring_register_function(state, b"__ring_rs_see\0", ring_rs_see);
+    ring_state_runcode(state, SEE_HOOK);
+}
+
+/// Set or remove the sink for `vm`.
+pub(crate) fn set_sink(vm: RingVM, sink: Option<Box<dyn Write>>) {
+    SINKS.with(|sinks| {
+        let mut sinks = sinks.borrow_mut();
+        sinks.retain(|(owner, _)| *owner != vm);
+        if let Some(sink) = sink {
+            sinks.push((vm, Rc::new(RefCell::new(sink))));
+        }
+    });
+}
+
+/// Called by `ringvm_see()` with the value being printed. Returns false if
+/// the VM has no sink, or its sink is busy because writing to it printed more
+/// Ring output, so Ring prints the value itself. A panicking sink becomes a
+/// Ring runtime error.
+extern "C" fn ring_rs_see(p: *mut c_void) {
+    ring_func_guard(p, |p| {
+        let vm = p as RingVM;
+        let sink = SINKS.with(|sinks| {
+            sinks
+                .borrow()
+                .iter()
+                .find(|(owner, _)| *owner == vm)
+                .map(|(_, sink)| sink.clone())
+        });
+        let written = match (sink, param_item(&p, 1)) {
+            (Some(sink), Ok(item)) => match sink.try_borrow_mut() {
+                Ok(mut sink) => {
+                    let mut bytes = Vec::new();
+                    see_item(vm, &mut bytes, item, 0);
+                    let _ = sink.write_all(&bytes);
+                    let _ = sink.flush();
+                    true
+                }
+                Err(_) => false,
+            },
+            _ => false,
+        };
+        ring_api_retnumber(p, if written { 1.0 } else { 0.0 });
+    });
+}
+
+/// Format a value the way `see` prints it: lists one item per line, objects
+/// one `name: value` attribute per line.
+fn see_item(vm: RingVM, out: &mut Vec<u8>, item: &Item, depth: usize) {
+    if let Some(list) = item_list(item) {
+        if depth < MAX_DEPTH {
+            see_list(vm, out, list, depth + 1);
+        }
+        return;
+    }
+    let item = deref_item(item);
+    match item.nType() {
+        ITEMTYPE_STRING => out.extend_from_slice(item_bytes(item).unwrap_or_default()),
+        ITEMTYPE_NUMBER => {
+            let n = ring_item_getnumber(item as *const Item as ffi::RingItem);
+            let mut buf = [0 as c_char; 512];
+            unsafe {
+                ffi::ring_vm_numtostring(vm, n, buf.as_mut_ptr());
+                out.extend_from_slice(CStr::from_ptr(buf.as_ptr()).to_bytes());
+            }
+        }
+        ITEMTYPE_POINTER => {
+            out.extend_from_slice(format!("{:p}", unsafe { item.data.pPointer }).as_bytes())
+        }
+        _ => {}
+    }
+}
+
+fn see_list(vm: RingVM, out: &mut Vec<u8>, list: ffi::RingList, depth: usize) {
+    if ring_list_isobject(list) {
+        let data = ring_list_getlist(list, OBJECT_DATA);
+        for i in 1..=ring_list_getsize(data) {
+            let var = ring_list_getlist(data, i);
+            let name = ring_list_getstring_str(var, RING_VAR_NAME);
+            if name == "self" {
+                continue;
+            }
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(b": ");
+            see_line(
+                vm,
+                out,
+                unsafe { &*ring_list_getitem(var, RING_VAR_VALUE) },
+                depth,
+            );
+        }
+        return;
+    }
+    for i in 1..=ring_list_getsize(list) {
+        see_line(vm, out, unsafe { &*ring_list_getitem(list, i) }, depth);
+    }
+}
+
+/// A list item on its own line; nested lists already end their lines.
+fn see_line(vm: RingVM, out: &mut Vec<u8>, item: &Item, depth: usize) {
+    let nested = item_list(item).is_some();
+    see_item(vm, out, item, depth);
+    if !nested {
+        out.push(b'\n');
+    }
+}
+
+/// A cloneable in-memory sink that collects script output.
+///
+/// ```rust,ignore
+/// let buffer = OutputBuffer::new();
+/// ring.set_output(buffer.clone());
+/// ring.run_code("? 'hello'");
+/// assert_eq!(buffer.take_string(), "hello\n");
+/// ```
+#[derive(Clone, Default)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy of the collected bytes.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+
+    /// Collected output as a string (invalid UTF-8 is replaced).
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    /// Return the collected output and clear the buffer.
+    pub fn take_string(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adapts a callback to [`Write`], for use as an output sink.
+pub struct OutputFn<F: FnMut(&[u8])>(pub F);
+
+impl<F: FnMut(&[u8])> Write for OutputFn<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.0)(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,762 @@
+//! Serde support (feature `serde`).
+//!
+//! [`RingSerializer`] writes any `Serialize` value straight into a Ring item,
+//! and [`RingDeserializer`] reads a `Deserialize` value back from one, without
+//! going through JSON or another text format:
+//!
+//! | Serde | Ring |
+//! |-------|------|
+//! | `bool` | number (1 or 0) |
+//! | integers, floats | number |
+//! | `char`, strings | string |
+//! | bytes | string (binary-safe), or a list of numbers when reading |
+//! | `None`, `()`, unit structs | empty string |
+//! | `Some(value)`, newtype structs | the value |
+//! | sequences, tuples, tuple structs | list |
+//! | maps, structs | list of `[key, value]` pairs, i.e. `[:name = value]` |
+//! | unit variants | the variant name |
+//! | other variants | `[variant, fields...]` |
+//!
+//! Enums have the same shape as the data enums of `ring_extension!`, struct
+//! variants included. Written lists with string keys are hashed like maps
+//! from [`IntoRing`]. Ring objects and C pointers can't be deserialized.
+//!
+//! Ring has no null, so `None` shares the empty string with `()` and `""`:
+//! `Some(String::new())` reads back as `None`.
+//!
+//! [`RingSerde`] wraps a value so it goes anywhere [`FromRing`] and
+//! [`IntoRing`] are accepted: parameters, return values, globals and
+//! callback arguments. `#[ring(serde)]` in `ring_extension!` uses it.
+//! Returned values are serialized before anything is returned, and a
+//! serialization error is raised in Ring.
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Job {
+//!     name: String,
+//!     retries: u32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! ring_func!(ring_job_check, |p| {
+//!     ring_check_paracount!(p, 1);
+//!     let RingSerde(mut job) = ring_get!(p, 1, RingSerde<Job>);
+//!     job.retries = job.retries.min(10);
+//!     ring_ret!(p, RingSerde(job));
+//! });
+//! ```
+//!
+//! ```ring
+//! job = job_check([:name = "build", :retries = 50, :tags = ["ci"]])
+//! ? job[:retries]  # 10
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::api::{ring_api_error_str, ring_api_newlist};
+use crate::convert::{
+    FromRing, IntoRing, deref_item, index_string_keys, is_none_item, is_string_keyed, item_bytes,
+    item_list, item_type_name, list_newitem, ret_item, type_error,
+};
+use crate::ffi::{
+    ITEMTYPE_LIST, ITEMTYPE_NOTHING, ITEMTYPE_NUMBER, ITEMTYPE_STRING, Item, RingItem,
+};
+use crate::ffi_types::{c_uint, c_void};
+use crate::item::{ring_item_setstring2, ring_item_settype};
+use crate::list::{
+    ring_list_addstring2, ring_list_getitem, ring_list_getsize, ring_list_iscpointerlist,
+    ring_list_isobject, ring_list_newlist,
+};
+use crate::{RingError, RingList, RingResult};
+
+impl ser::Error for RingError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RingError::new(msg.to_string())
+    }
+}
+
+impl de::Error for RingError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RingError::new(msg.to_string())
+    }
+}
+
+/// A value passed to and from Ring through serde.
+///
+/// ```rust,ignore
+/// let RingSerde(config) = ring.get_global::<RingSerde<Config>>("config")?;
+/// ring.set_global("config", RingSerde(&config));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RingSerde<T>(pub T);
+
+impl<'a, T: Deserialize<'a>> FromRing<'a> for RingSerde<T> {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        T::deserialize(RingDeserializer::new(item)).map(RingSerde)
+    }
+}
+
+impl<T: Serialize> RingSerde<T> {
+    /// Write the value into an item, replacing its content.
+    ///
+    /// Unlike [`IntoRing::into_item`] this returns serialization errors; the
+    /// item may then hold part of the value.
+    pub fn to_item(&self, item: RingItem) -> RingResult<()> {
+        self.0.serialize(RingSerializer::new(item))
+    }
+}
+
+/// A value that fails to serialize is written as an empty string where the
+/// error can't be returned: globals, list elements, callback arguments. As a
+/// return value the error is raised in Ring; [`RingSerde::to_item`] returns
+/// it.
+impl<T: Serialize> IntoRing for RingSerde<T> {
+    fn into_item(self, item: RingItem) {
+        if self.to_item(item).is_err() {
+            ring_item_setstring2(item, b"");
+        }
+    }
+
+    fn ret(self, p: *mut c_void) {
+        let item = list_newitem(ring_api_newlist(p));
+        match self.0.serialize(RingSerializer::new(item)) {
+            Ok(()) => ret_item(p, item),
+            Err(e) => ring_api_error_str(p, &e.message),
+        }
+    }
+}
+
+/// Serializes a value into a Ring item, replacing its content.
+pub struct RingSerializer {
+    item: RingItem,
+}
+
+impl RingSerializer {
+    pub fn new(item: RingItem) -> Self {
+        Self { item }
+    }
+
+    fn into_list(self) -> RingList {
+        ring_item_settype(self.item, ITEMTYPE_LIST);
+        unsafe { (*self.item).data.pList }
+    }
+
+    /// A `[variant, ...]` list.
+    fn into_variant(self, variant: &str) -> RingList {
+        let list = self.into_list();
+        ring_list_addstring2(list, variant.as_bytes());
+        list
+    }
+}
+
+/// Writes an item into a new element of `list`.
+fn serialize_into<T: Serialize + ?Sized>(value: &T, list: RingList) -> RingResult<()> {
+    value.serialize(RingSerializer::new(list_newitem(list)))
+}
+
+macro_rules! serialize_with_into_ring {
+    ($($method:ident: $ty:ty),*) => {$(
+        fn $method(self, v: $ty) -> RingResult<()> {
+            v.into_item(self.item);
+            Ok(())
+        }
+    )*};
+}
+
+impl Serializer for RingSerializer {
+    type Ok = ();
+    type Error = RingError;
+    type SerializeSeq = RingListSerializer;
+    type SerializeTuple = RingListSerializer;
+    type SerializeTupleStruct = RingListSerializer;
+    type SerializeTupleVariant = RingListSerializer;
+    type SerializeMap = RingPairsSerializer;
+    type SerializeStruct = RingPairsSerializer;
+    type SerializeStructVariant = RingListSerializer;
+
+    serialize_with_into_ring!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+        serialize_bytes: &[u8]
+    );
+
+    fn serialize_none(self) -> RingResult<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> RingResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> RingResult<()> {
+        ring_item_setstring2(self.item, b"");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> RingResult<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> RingResult<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> RingResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> RingResult<()> {
+        serialize_into(value, self.into_variant(variant))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> RingResult<RingListSerializer> {
+        Ok(RingListSerializer {
+            list: self.into_list(),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> RingResult<RingListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> RingResult<RingListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> RingResult<RingListSerializer> {
+        Ok(RingListSerializer {
+            list: self.into_variant(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> RingResult<RingPairsSerializer> {
+        Ok(RingPairsSerializer {
+            list: self.into_list(),
+            pair: std::ptr::null_mut(),
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> RingResult<RingPairsSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> RingResult<RingListSerializer> {
+        self.serialize_tuple_variant(name, index, variant, len)
+    }
+}
+
+/// Serializes the elements of a list, or the fields of a variant.
+pub struct RingListSerializer {
+    list: RingList,
+}
+
+impl ser::SerializeSeq for RingListSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> RingResult<()> {
+        serialize_into(value, self.list)
+    }
+
+    fn end(self) -> RingResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for RingListSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> RingResult<()> {
+        serialize_into(value, self.list)
+    }
+
+    fn end(self) -> RingResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for RingListSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> RingResult<()> {
+        serialize_into(value, self.list)
+    }
+
+    fn end(self) -> RingResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for RingListSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> RingResult<()> {
+        serialize_into(value, self.list)
+    }
+
+    fn end(self) -> RingResult<()> {
+        Ok(())
+    }
+}
+
+/// Struct variant fields are positional, like the other variants.
+impl ser::SerializeStructVariant for RingListSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> RingResult<()> {
+        serialize_into(value, self.list)
+    }
+
+    fn end(self) -> RingResult<()> {
+        Ok(())
+    }
+}
+
+/// Serializes map entries and struct fields as `[key, value]` pairs.
+pub struct RingPairsSerializer {
+    list: RingList,
+    /// The pair whose value comes next.
+    pair: RingList,
+}
+
+impl ser::SerializeMap for RingPairsSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> RingResult<()> {
+        self.pair = ring_list_newlist(self.list);
+        serialize_into(key, self.pair)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> RingResult<()> {
+        serialize_into(value, self.pair)
+    }
+
+    fn end(self) -> RingResult<()> {
+        index_string_keys(self.list);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for RingPairsSerializer {
+    type Ok = ();
+    type Error = RingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> RingResult<()> {
+        let pair = ring_list_newlist(self.list);
+        ring_list_addstring2(pair, key.as_bytes());
+        serialize_into(value, pair)
+    }
+
+    fn end(self) -> RingResult<()> {
+        index_string_keys(self.list);
+        Ok(())
+    }
+}
+
+/// Deserializes a value from a Ring item. Strings are borrowed from the item
+/// where the target type allows it.
+pub struct RingDeserializer<'de> {
+    item: &'de Item,
+}
+
+impl<'de> RingDeserializer<'de> {
+    pub fn new(item: &'de Item) -> Self {
+        Self { item }
+    }
+
+    /// The list the item holds; C pointers and objects are not lists here.
+    fn list(&self) -> RingResult<RingList> {
+        let list = item_list(self.item).ok_or_else(|| type_error("a list", self.item))?;
+        if ring_list_iscpointerlist(list) {
+            return Err(RingError::new("expected a list, found a C pointer"));
+        }
+        if ring_list_isobject(list) {
+            return Err(RingError::new("expected a list, found an object"));
+        }
+        Ok(list)
+    }
+}
+
+macro_rules! deserialize_with_from_ring {
+    ($($method:ident => $visit:ident: $ty:ty),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+            visitor.$visit(<$ty>::from_item(self.item)?)
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for RingDeserializer<'de> {
+    type Error = RingError;
+
+    /// Numbers without a fraction are read as integers, and lists of
+    /// `[string, value]` pairs as maps.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        if item_list(self.item).is_some() {
+            let list = self.list()?;
+            return if is_string_keyed(list) {
+                visitor.visit_map(PairsAccess::new(list))
+            } else {
+                visit_list(visitor, list, 1)
+            };
+        }
+        let item = deref_item(self.item);
+        match item.nType() {
+            ITEMTYPE_NUMBER => {
+                let n = f64::from_item(item)?;
+                if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+                    visitor.visit_i64(n as i64)
+                } else {
+                    visitor.visit_f64(n)
+                }
+            }
+            ITEMTYPE_STRING => {
+                let bytes = item_bytes(item)?;
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            ITEMTYPE_NOTHING => visitor.visit_unit(),
+            _ => Err(RingError::new(format!(
+                "a {} can't be deserialized",
+                item_type_name(item)
+            ))),
+        }
+    }
+
+    deserialize_with_from_ring!(
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+        deserialize_str => visit_borrowed_str: &'de str,
+        deserialize_string => visit_borrowed_str: &'de str,
+        deserialize_identifier => visit_borrowed_str: &'de str
+    );
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        if item_list(self.item).is_some() {
+            return self.deserialize_seq(visitor);
+        }
+        visitor.visit_borrowed_bytes(item_bytes(self.item)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        if is_none_item(self.item) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        if !is_none_item(self.item) {
+            return Err(type_error("an empty string", self.item));
+        }
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        visit_list(visitor, self.list()?, 1)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> RingResult<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        visitor.visit_map(PairsAccess::new(self.list()?))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    /// A variant name, or a `[variant, fields...]` list.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        if item_list(self.item).is_none() {
+            let variant = <&'de str>::from_item(self.item)?;
+            return visitor.visit_enum(variant.into_deserializer());
+        }
+        let list = self.list()?;
+        if ring_list_getsize(list) == 0 {
+            return Err(RingError::new("expected [variant, fields...], found []"));
+        }
+        let variant = <&'de str>::from_item(list_item(list, 1))?;
+        visitor.visit_enum(VariantAccess { list, variant })
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> RingResult<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+fn list_item<'de>(list: RingList, index: c_uint) -> &'de Item {
+    unsafe { &*ring_list_getitem(list, index) }
+}
+
+/// Visit the items of `list` from `first` on, which must all be used.
+fn visit_list<'de, V: Visitor<'de>>(
+    visitor: V,
+    list: RingList,
+    first: c_uint,
+) -> RingResult<V::Value> {
+    let mut access = ListAccess {
+        list,
+        next: first,
+        size: ring_list_getsize(list),
+        _marker: PhantomData,
+    };
+    let value = visitor.visit_seq(&mut access)?;
+    if access.next <= access.size {
+        let used = (access.next - first) as usize;
+        return Err(de::Error::invalid_length(
+            (access.size + 1 - first) as usize,
+            &format!("{used} items").as_str(),
+        ));
+    }
+    Ok(value)
+}
+
+struct ListAccess<'de> {
+    list: RingList,
+    next: c_uint,
+    size: c_uint,
+    _marker: PhantomData<&'de Item>,
+}
+
+impl<'de> de::SeqAccess<'de> for ListAccess<'de> {
+    type Error = RingError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> RingResult<Option<T::Value>> {
+        if self.next > self.size {
+            return Ok(None);
+        }
+        let item = list_item(self.list, self.next);
+        self.next += 1;
+        seed.deserialize(RingDeserializer::new(item)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.size + 1 - self.next) as usize)
+    }
+}
+
+/// The entries of a list of `[key, value]` pairs.
+struct PairsAccess<'de> {
+    list: RingList,
+    next: c_uint,
+    size: c_uint,
+    value: Option<&'de Item>,
+}
+
+impl PairsAccess<'_> {
+    fn new(list: RingList) -> Self {
+        Self {
+            list,
+            next: 1,
+            size: ring_list_getsize(list),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for PairsAccess<'de> {
+    type Error = RingError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> RingResult<Option<K::Value>> {
+        if self.next > self.size {
+            return Ok(None);
+        }
+        let item = list_item(self.list, self.next);
+        self.next += 1;
+        let pair = item_list(item)
+            .filter(|&pair| ring_list_getsize(pair) == 2)
+            .ok_or_else(|| type_error("a [key, value] pair", item))?;
+        self.value = Some(list_item(pair, 2));
+        seed.deserialize(RingDeserializer::new(list_item(pair, 1)))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> RingResult<V::Value> {
+        let item = self
+            .value
+            .take()
+            .ok_or_else(|| RingError::new("map value requested before its key"))?;
+        seed.deserialize(RingDeserializer::new(item))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.size + 1 - self.next) as usize)
+    }
+}
+
+/// A `[variant, fields...]` list.
+struct VariantAccess<'de> {
+    list: RingList,
+    variant: &'de str,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'de> {
+    type Error = RingError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> RingResult<(V::Value, Self)> {
+        let variant = IntoDeserializer::<RingError>::into_deserializer(self.variant);
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> {
+    fn expect_fields(&self, count: c_uint) -> RingResult<()> {
+        check_field_count(self.variant, count, ring_list_getsize(self.list) - 1)
+    }
+}
+
+pub(crate) fn check_field_count(variant: &str, count: c_uint, found: c_uint) -> RingResult<()> {
+    if found != count {
+        return Err(RingError::new(format!(
+            "variant {variant} expects {count} field(s), found {found}"
+        )));
+    }
+    Ok(())
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = RingError;
+
+    fn unit_variant(self) -> RingResult<()> {
+        self.expect_fields(0)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> RingResult<T::Value> {
+        self.expect_fields(1)?;
+        seed.deserialize(RingDeserializer::new(list_item(self.list, 2)))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> RingResult<V::Value> {
+        visit_list(visitor, self.list, 2)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> RingResult<V::Value> {
+        visit_list(visitor, self.list, 2)
+    }
+}
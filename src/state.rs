@@ -1,7 +1,10 @@
 use crate::ffi_types::{CString, c_char, c_int, c_uint, c_void, size_t};
 
+use crate::convert::{IntoRing, ring_var_set};
 use crate::ffi;
-use crate::{RingList, RingState};
+use crate::interpreter::state_vm;
+use crate::list::{ring_list_getlist, ring_list_getsize, ring_list_getstring_str};
+use crate::{RING_VAR_NAME, RingList, RingState, RingVM};
 
 #[inline]
 pub fn ring_state_new() -> RingState {
@@ -61,6 +64,74 @@ pub fn ring_state_newvar_str(state: RingState, name: &str) -> RingList {
     }
 }
 
+/// The VM of a state from [`ring_state_init`], such as the one passed to
+/// `ringlib_init`. A state from [`ring_state_new`] has no VM to run code on
+/// and must not be passed.
+///
+/// The state doesn't expose its VM, so this runs a line of Ring code to get
+/// it; look it up once when setting several variables.
+pub fn ring_state_vm(state: RingState) -> RingVM {
+    state_vm(state)
+}
+
+/// Set global variable `name` (case-insensitive) to `value`, creating it if
+/// needed.
+///
+/// This looks up the VM with [`ring_state_vm`] on every call; use
+/// [`ring_vm_setglobal`] to set several variables.
+/// Returns `false` if the variable couldn't be created.
+pub fn ring_state_setvar(state: RingState, name: &str, value: impl IntoRing) -> bool {
+    ring_vm_setglobal(state_vm(state), name, value)
+}
+
+/// Set global variable `name` (case-insensitive) of `vm` to `value`, creating
+/// it if needed.
+///
+/// The variable is defined in the VM's global scope, also when `loadlib()`
+/// runs inside a function, and a local of the same name is left alone.
+/// Returns `false` if `vm` is null or the variable couldn't be created.
+pub fn ring_vm_setglobal(vm: RingVM, name: &str, value: impl IntoRing) -> bool {
+    if vm.is_null() {
+        return false;
+    }
+    let state = unsafe { (*vm).pRingState } as RingState;
+    let name = name.to_ascii_lowercase();
+    let scope = global_scope(vm);
+    let var = match find_scope_var(scope, &name) {
+        Some(var) => var,
+        // ring_state_newvar() creates the variable in the active scope.
+        None => unsafe {
+            let active = std::mem::replace(&mut (*vm).pActiveMem, scope);
+            let var = ring_state_newvar_str(state, &name);
+            (*vm).pActiveMem = active;
+            var
+        },
+    };
+    if var.is_null() {
+        return false;
+    }
+    ring_var_set(var, value);
+    true
+}
+
+/// The global scope of the running code, found like `ring_vm_getglobalscope()`.
+fn global_scope(vm: RingVM) -> RingList {
+    unsafe {
+        let scopes = (*vm).pGlobalScopes;
+        let current = (*vm).nCurrentGlobalScope;
+        if current == 0 || scopes.is_null() || current > ring_list_getsize(scopes) {
+            return &raw mut (*vm).aScopes[0];
+        }
+        ring_list_getlist(scopes, current)
+    }
+}
+
+fn find_scope_var(scope: RingList, name: &str) -> Option<RingList> {
+    (1..=ring_list_getsize(scope))
+        .map(|i| ring_list_getlist(scope, i))
+        .find(|&var| ring_list_getstring_str(var, RING_VAR_NAME) == name)
+}
+
 #[inline]
 pub fn ring_state_runfile(state: RingState, filename: &[u8]) -> c_int {
     unsafe { ffi::ring_state_runfile(state, filename.as_ptr() as *const c_char) }
@@ -47,6 +47,8 @@ fn test_api_error_constants() {
 /// Verify pointer type constants
 #[test]
 fn test_pointer_type_constants() {
+    assert_eq!(crate::RING_CPOINTER_POINTER, 1);
+    assert_eq!(crate::RING_CPOINTER_TYPE, 2);
     assert_eq!(crate::RING_CPOINTER_STATUS, 3);
     assert_eq!(crate::RING_CPOINTERSTATUS_NOTASSIGNED, 2);
 }
@@ -58,3 +60,348 @@ fn test_output_constants() {
     assert_eq!(crate::RING_OUTPUT_RETLISTBYREF, 1);
     assert_eq!(crate::RING_OUTPUT_RETNEWREF, 2);
 }
+
+/// Verify RingError formats its location
+#[test]
+fn test_ring_error_display() {
+    let mut error = crate::RingError::new("Error (R24) : Using uninitialized variable : x");
+    assert_eq!(
+        error.to_string(),
+        "Error (R24) : Using uninitialized variable : x"
+    );
+
+    error.file = Some("script.ring".to_string());
+    error.line = Some(3);
+    error.function = Some("main".to_string());
+    assert_eq!(
+        error.to_string(),
+        "Error (R24) : Using uninitialized variable : x in function main() at script.ring:3"
+    );
+}
+
+/// Verify serde errors become plain RingErrors
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_error() {
+    let error = <crate::RingError as serde::de::Error>::missing_field("name");
+    assert_eq!(error.message, "missing field `name`");
+    assert_eq!(error.file, None);
+}
+
+/// Verify serde reads empty and string items by their shape
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_item_shapes() {
+    use serde::Deserialize;
+
+    use crate::RingDeserializer;
+    use crate::ffi::{ITEMTYPE_NOTHING, ITEMTYPE_STRING, ItemData};
+
+    fn read<'a, T: Deserialize<'a>>(item: &'a Item) -> crate::RingResult<T> {
+        T::deserialize(RingDeserializer::new(item))
+    }
+
+    let nothing = Item {
+        data: ItemData { iNumber: 0 },
+        flags: ITEMTYPE_NOTHING,
+        pGCFreeFunc: None,
+    };
+    assert_eq!(read::<Option<u32>>(&nothing).unwrap(), None);
+    read::<()>(&nothing).unwrap();
+    assert_eq!(
+        read::<Vec<u32>>(&nothing).unwrap_err().message,
+        "expected a list, found nothing"
+    );
+
+    fn string_item(string: &mut RingString) -> Item {
+        Item {
+            data: ItemData { pString: string },
+            flags: ITEMTYPE_STRING,
+            pGCFreeFunc: None,
+        }
+    }
+    fn ring_string(text: &mut [u8]) -> RingString {
+        RingString {
+            cStr: text.as_mut_ptr().cast(),
+            nSize: text.len() as _,
+            nCapacity: text.len() as _,
+            cStrArray: [0; 32],
+        }
+    }
+
+    let mut text = *b"Ok";
+    let mut string = ring_string(&mut text);
+    let item = string_item(&mut string);
+    assert_eq!(read::<&str>(&item).unwrap(), "Ok");
+    assert_eq!(
+        read::<Option<String>>(&item).unwrap().as_deref(),
+        Some("Ok")
+    );
+    assert_eq!(
+        read::<u32>(&item).unwrap_err().message,
+        "expected a number, found string"
+    );
+    assert_eq!(
+        read::<std::collections::HashMap<String, u32>>(&item)
+            .unwrap_err()
+            .message,
+        "expected a list, found string"
+    );
+    assert_eq!(
+        read::<()>(&item).unwrap_err().message,
+        "expected an empty string, found string"
+    );
+
+    // A bare string is a unit variant; variants with fields need a list.
+    let error = read::<Result<u32, u32>>(&item).unwrap_err();
+    assert!(error.message.contains("unit variant"), "{}", error.message);
+    let mut text = *b"No";
+    let mut string = ring_string(&mut text);
+    let error = read::<Result<u32, u32>>(&string_item(&mut string)).unwrap_err();
+    assert!(
+        error.message.contains("unknown variant `No`"),
+        "{}",
+        error.message
+    );
+
+    // `None` is written as an empty string, so `Some("")` reads back as `None`.
+    let mut string = ring_string(&mut []);
+    let empty = string_item(&mut string);
+    assert_eq!(read::<Option<String>>(&empty).unwrap(), None);
+    assert_eq!(read::<String>(&empty).unwrap(), "");
+    read::<()>(&empty).unwrap();
+}
+
+/// Verify variant lists must hold exactly the variant's fields
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_variant_field_count() {
+    use crate::serde_bridge::check_field_count;
+
+    check_field_count("Move", 2, 2).unwrap();
+    assert_eq!(
+        check_field_count("Move", 2, 1).unwrap_err().message,
+        "variant Move expects 2 field(s), found 1"
+    );
+    assert_eq!(
+        check_field_count("Quit", 0, 1).unwrap_err().message,
+        "variant Quit expects 0 field(s), found 1"
+    );
+}
+
+/// Verify RingValue accessors and equality
+#[test]
+fn test_ring_value_accessors() {
+    use crate::RingValue;
+
+    let value = RingValue::List(vec![RingValue::from(1.5), RingValue::from("text")]);
+    let items = value.as_list().unwrap();
+    assert_eq!(items[0].as_number(), Some(1.5));
+    assert_eq!(items[1].as_str(), Some("text"));
+    assert!(RingValue::Nothing.is_nothing());
+
+    let object = RingValue::Object {
+        class: "Point".to_string(),
+        class_ptr: std::ptr::null_mut(),
+        attributes: vec![("x".to_string(), RingValue::Number(3.0))],
+    };
+    assert_eq!(object.attribute("X"), Some(&RingValue::Number(3.0)));
+    assert_ne!(object, value);
+}
+
+/// Verify an object without a class pointer is rejected before anything is written
+#[test]
+fn test_ring_value_object_without_class() {
+    use crate::RingValue;
+
+    let object = RingValue::Object {
+        class: "Point".to_string(),
+        class_ptr: std::ptr::null_mut(),
+        attributes: vec![],
+    };
+    let nested = RingValue::List(vec![RingValue::Number(1.0), object]);
+    let error = nested.to_item(std::ptr::null_mut()).unwrap_err();
+    assert!(error.message.contains("'Point'"));
+}
+
+/// Verify a value deleted while a view into it is alive is dropped with the
+/// last view
+#[test]
+fn test_ring_pointer_view_keeps_parent() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Inner(u8);
+    struct Outer {
+        inner: Inner,
+    }
+    impl Drop for Outer {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    crate::ring_pointer_type!(@impl Inner, "Inner");
+    crate::ring_pointer_type!(@impl Outer, "Outer");
+
+    let outer = Box::into_raw(Box::new(Outer { inner: Inner(7) }));
+    let inner: *mut Inner = unsafe { &mut (*outer).inner };
+    crate::convert::add_view(outer, inner);
+    crate::convert::add_view(outer, inner);
+
+    crate::ring_pointer_drop(outer);
+    crate::ring_pointer_drop(inner);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+    assert_eq!(unsafe { (*inner).0 }, 7);
+
+    crate::ring_pointer_drop(inner);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+}
+
+/// Verify RingBytes converts to and from Vec<u8>
+#[test]
+fn test_ring_bytes() {
+    use crate::RingBytes;
+
+    let bytes = RingBytes::from(vec![0, 255]);
+    assert_eq!(&bytes[..], &[0, 255]);
+    assert_eq!(Vec::from(bytes), vec![0, 255]);
+}
+
+// The tests below run Ring code, so they need a working Ring library at run
+// time: `RUSTFLAGS="--cfg ring_vm_tests" cargo test`.
+
+/// Verify interpreters own separate states and VMs, and the compiler has none
+#[cfg(ring_vm_tests)]
+#[test]
+fn test_vm_interpreters() {
+    use crate::Ring;
+
+    let first = Ring::new();
+    let second = Ring::new();
+    assert!(first.has_vm() && second.has_vm());
+    assert_ne!(first.vm_ptr(), second.vm_ptr());
+    assert_eq!(crate::ring_state_vm(first.as_ptr()), first.vm_ptr());
+
+    first.run_code("x = 1");
+    assert!(first.find_var("x").is_some());
+    assert!(second.find_var("x").is_none());
+    drop(second);
+    first.run_code("x++");
+    assert_eq!(first.get_global::<f64>("x").unwrap(), 2.0);
+
+    assert!(!Ring::compiler().has_vm());
+}
+
+/// Verify eval runs code and returns runtime errors with their location
+#[cfg(ring_vm_tests)]
+#[test]
+fn test_vm_eval() {
+    use crate::Ring;
+
+    let ring = Ring::new();
+    ring.eval("x = 6 * 7").unwrap();
+    assert_eq!(ring.get_global::<f64>("x").unwrap(), 42.0);
+
+    let error = ring
+        .eval("func check n if n < 0 raise('negative') ok\ncheck(-1)")
+        .unwrap_err();
+    assert!(error.message.contains("negative"), "{}", error.message);
+    assert_eq!(error.function.as_deref(), Some("check"));
+    assert_eq!(error.line, Some(1));
+
+    // The VM keeps running after an error.
+    ring.eval("x++").unwrap();
+    assert_eq!(ring.get_global::<f64>("x").unwrap(), 43.0);
+}
+
+/// Verify globals set from Rust are read back, by Ring and by Rust
+#[cfg(ring_vm_tests)]
+#[test]
+fn test_vm_globals() {
+    use crate::Ring;
+
+    let ring = Ring::new();
+    ring.set_global("Name", "O'Brien \"quoted\"");
+    ring.set_global("items", vec![1, 2, 3]);
+    ring.eval("count = len(items)  name += '!'").unwrap();
+    assert_eq!(ring.get_global::<f64>("COUNT").unwrap(), 3.0);
+    assert_eq!(
+        ring.get_global::<String>("name").unwrap(),
+        "O'Brien \"quoted\"!"
+    );
+
+    let error = ring.get_global::<f64>("name").unwrap_err();
+    assert!(error.message.contains("variable name"), "{}", error.message);
+    assert!(ring.get_global::<f64>("missing").is_err());
+}
+
+/// Verify calling Ring functions with list arguments, which are passed as copies
+#[cfg(ring_vm_tests)]
+#[test]
+fn test_vm_call_lists() {
+    use crate::{Ring, ring_vm_call};
+
+    let ring = Ring::new();
+    ring.run_code(
+        "func total items
+            sum = 0
+            for x in items sum += x next
+            return sum
+        func grow items
+            items + 4
+            return items",
+    );
+    let sum: f64 = ring.call("total", (vec![1.0, 2.0, 3.0],)).unwrap();
+    assert_eq!(sum, 6.0);
+
+    let items = vec![1.0];
+    let grown: Vec<f64> = ring.call("grow", (&items,)).unwrap();
+    assert_eq!(grown, [1.0, 4.0]);
+    assert_eq!(items, [1.0]);
+
+    let nested: Vec<Vec<String>> =
+        ring_vm_call(ring.vm_ptr(), "reverse", (vec![vec!["a"], vec!["b", "c"]],)).unwrap();
+    assert_eq!(nested, [vec!["b", "c"], vec!["a"]]);
+
+    let error = ring.call::<f64, _>("missing", ()).unwrap_err();
+    assert!(error.message.contains("missing"), "{}", error.message);
+    assert_eq!(ring.call::<f64, _>("total", (vec![5.0],)).unwrap(), 5.0);
+}
+
+/// Verify script output goes to the sink and back to the terminal once reset
+#[cfg(ring_vm_tests)]
+#[test]
+fn test_vm_output_sink() {
+    use crate::{OutputBuffer, Ring};
+
+    let ring = Ring::new();
+    let buffer = OutputBuffer::new();
+    ring.set_output(buffer.clone());
+    ring.eval("see 'hello' + nl  ? 42  see [1, 'two']").unwrap();
+    assert_eq!(buffer.take_string(), "hello\n42\n1\ntwo\n");
+
+    // Each interpreter has its own sink.
+    let other = Ring::new();
+    let other_buffer = OutputBuffer::new();
+    other.set_output(other_buffer.clone());
+    other.eval("? 'elsewhere'").unwrap();
+    assert_eq!(other_buffer.take_string(), "elsewhere\n");
+    assert_eq!(buffer.take_string(), "");
+
+    ring.reset_output();
+    ring.eval("see nl").unwrap();
+    assert_eq!(buffer.take_string(), "");
+}
+
+/// Verify a panicking output sink becomes a Ring error
+#[cfg(all(ring_vm_tests, not(feature = "no-catch-unwind")))]
+#[test]
+fn test_vm_output_sink_panic() {
+    use crate::Ring;
+
+    let ring = Ring::new();
+    ring.set_output_fn(|_| panic!("sink failed"));
+    let error = ring.eval("see 'x'").unwrap_err();
+    assert!(error.message.contains("sink failed"), "{}", error.message);
+}
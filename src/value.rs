@@ -0,0 +1,408 @@
+//! Owned Ring values.
+//!
+//! [`RingValue`] is a Rust-side copy of anything a Ring item can hold, for
+//! moving whole values (nested lists, objects) across the boundary at once.
+
+use crate::convert::{
+    FromRing, IntoRing, deref_item, item_bytes, item_list, list_newitem, with_var_item,
+};
+use crate::ffi::{
+    ITEMTYPE_FUNCPOINTER, ITEMTYPE_LIST, ITEMTYPE_NOTHING, ITEMTYPE_NUMBER, ITEMTYPE_POINTER,
+    ITEMTYPE_STRING, Item, RingItem,
+};
+use crate::ffi_types::{c_uint, c_void};
+use crate::item::{
+    ring_item_getnumber, ring_item_setdouble, ring_item_setpointer, ring_item_setstring2,
+    ring_item_settype,
+};
+use crate::list::{
+    ring_list_addint, ring_list_addpointer, ring_list_addstring2, ring_list_getitem,
+    ring_list_getlist, ring_list_getpointer, ring_list_getsize, ring_list_getstring_str,
+    ring_list_iscpointerlist, ring_list_isobject, ring_list_newlist, ring_list_setpointer,
+};
+use crate::{
+    CPointer, RING_CPOINTERSTATUS_NOTCOPIED, RING_OBJTYPE_LISTITEM, RING_VAR_NAME, RING_VAR_VALUE,
+    RING_VM_POINTER, RingError, RingFunc, RingList, RingResult,
+};
+
+/// Index of the class pointer in an object list.
+const OBJECT_CLASS: c_uint = 1;
+/// Index of the attribute variables in an object list.
+const OBJECT_DATA: c_uint = 2;
+/// Lists nested deeper than this are not copied.
+const MAX_DEPTH: usize = 256;
+
+/// A Ring value owned by Rust.
+///
+/// ```rust,ignore
+/// let value: RingValue = ring.get_global("config")?;
+/// if let RingValue::List(items) = &value {
+///     for item in items {
+///         println!("{item:?}");
+///     }
+/// }
+/// ring.set_global("copy", value);
+/// ```
+#[derive(Debug, Clone)]
+pub enum RingValue {
+    /// An empty item.
+    Nothing,
+    Number(f64),
+    /// Ring strings are binary-safe, so the bytes are kept as is.
+    String(Vec<u8>),
+    List(Vec<RingValue>),
+    /// An object with its attributes in declaration order (`self` excluded).
+    ///
+    /// `class_ptr` is the VM's class entry the object was read from. A class
+    /// can only be found through the VM, so an object built in Rust with a
+    /// null `class_ptr` can't be written back to Ring as an object; it is
+    /// written as a list of `[name, value]` pairs instead, like `[:x = 1]`.
+    Object {
+        class: String,
+        class_ptr: *mut c_void,
+        attributes: Vec<(String, RingValue)>,
+    },
+    CPointer {
+        ptr: *mut c_void,
+        type_name: String,
+    },
+    FuncPointer(RingFunc),
+    /// A raw pointer item (not a C pointer list), as added by `ring_list_addpointer`.
+    Pointer(*mut c_void),
+}
+
+impl RingValue {
+    /// Copy the value held by an item. References are followed.
+    ///
+    /// Fails on a list that contains itself, or nests deeper than 256 levels.
+    pub fn from_item(item: &Item) -> RingResult<Self> {
+        Reader::default().item(item)
+    }
+
+    /// Copy a list, recognizing objects and C pointer lists.
+    ///
+    /// Fails on a list that contains itself, or nests deeper than 256 levels.
+    pub fn from_list(list: RingList) -> RingResult<Self> {
+        Reader::default().list(list)
+    }
+
+    /// Write the value into an item, replacing its content.
+    ///
+    /// Fails, leaving the item unchanged, if the value contains an object
+    /// with a null `class_ptr`. [`IntoRing`] writes such an object as a list
+    /// of `[name, value]` pairs.
+    pub fn to_item(&self, item: RingItem) -> RingResult<()> {
+        self.check_writable()?;
+        self.write(item);
+        Ok(())
+    }
+
+    fn check_writable(&self) -> RingResult<()> {
+        match self {
+            Self::List(values) => values.iter().try_for_each(Self::check_writable),
+            Self::Object {
+                class, class_ptr, ..
+            } if class_ptr.is_null() => Err(RingError::new(format!(
+                "object of class '{class}' has no class pointer and can't be written to Ring"
+            ))),
+            Self::Object { attributes, .. } => attributes
+                .iter()
+                .try_for_each(|(_, value)| value.check_writable()),
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&self, item: RingItem) {
+        match self {
+            Self::Nothing => ring_item_settype(item, ITEMTYPE_NOTHING),
+            Self::Number(n) => ring_item_setdouble(item, *n),
+            Self::String(bytes) => ring_item_setstring2(item, bytes),
+            Self::List(values) => {
+                let list = new_item_list(item);
+                for value in values {
+                    value.write(list_newitem(list));
+                }
+            }
+            Self::Object {
+                class_ptr,
+                attributes,
+                ..
+            } if class_ptr.is_null() => {
+                let list = new_item_list(item);
+                for (name, value) in attributes {
+                    let pair = ring_list_newlist(list);
+                    ring_list_addstring2(pair, name.as_bytes());
+                    value.write(list_newitem(pair));
+                }
+            }
+            Self::Object {
+                class_ptr,
+                attributes,
+                ..
+            } => {
+                let list = new_item_list(item);
+                ring_list_addpointer(list, *class_ptr);
+                let data = ring_list_newlist(list);
+                let this = ring_list_newlist(data);
+                ring_list_addstring2(this, b"self");
+                ring_list_addint(this, RING_VM_POINTER);
+                ring_list_addpointer(this, item as *mut c_void);
+                ring_list_addint(this, RING_OBJTYPE_LISTITEM);
+                ring_list_addint(this, 0);
+                for (name, value) in attributes {
+                    let var = ring_list_newlist(data);
+                    ring_list_addstring2(var, name.as_bytes());
+                    ring_list_addint(var, 0);
+                    ring_list_addint(var, 0);
+                    ring_list_addint(var, 0);
+                    ring_list_addint(var, 0);
+                    crate::ring_var_set(var, value);
+                }
+            }
+            Self::CPointer { ptr, type_name } => {
+                let list = new_item_list(item);
+                ring_list_addpointer(list, *ptr);
+                ring_list_addstring2(list, type_name.as_bytes());
+                ring_list_addint(list, RING_CPOINTERSTATUS_NOTCOPIED);
+            }
+            Self::FuncPointer(func) => {
+                ring_item_settype(item, ITEMTYPE_FUNCPOINTER);
+                unsafe { (*item).data.pFunc = Some(*func) };
+            }
+            Self::Pointer(ptr) => ring_item_setpointer(item, *ptr),
+        }
+    }
+
+    /// Append the value to a list.
+    ///
+    /// Fails, adding nothing, under the same conditions as [`to_item`](Self::to_item).
+    pub fn add_to_list(&self, list: RingList) -> RingResult<()> {
+        self.check_writable()?;
+        self.write(list_newitem(list));
+        Ok(())
+    }
+
+    pub fn is_nothing(&self) -> bool {
+        matches!(self, Self::Nothing)
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// The string value, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[RingValue]> {
+        match self {
+            Self::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// An object attribute by name (case-insensitive, as in Ring).
+    pub fn attribute(&self, name: &str) -> Option<&RingValue> {
+        match self {
+            Self::Object { attributes, .. } => attributes
+                .iter()
+                .find(|(attr, _)| attr.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for RingValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nothing, Self::Nothing) => true,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::List(a), Self::List(b)) => a == b,
+            (
+                Self::Object {
+                    class: a,
+                    attributes: a_attrs,
+                    ..
+                },
+                Self::Object {
+                    class: b,
+                    attributes: b_attrs,
+                    ..
+                },
+            ) => a.eq_ignore_ascii_case(b) && a_attrs == b_attrs,
+            (
+                Self::CPointer {
+                    ptr: a,
+                    type_name: a_type,
+                },
+                Self::CPointer {
+                    ptr: b,
+                    type_name: b_type,
+                },
+            ) => a == b && a_type == b_type,
+            (Self::FuncPointer(a), Self::FuncPointer(b)) => *a as usize == *b as usize,
+            (Self::Pointer(a), Self::Pointer(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Copies Ring values, tracking the lists being copied to detect cycles.
+#[derive(Default)]
+struct Reader {
+    /// Lists from the outermost one down to the one being copied.
+    path: Vec<RingList>,
+}
+
+impl Reader {
+    fn item(&mut self, item: &Item) -> RingResult<RingValue> {
+        if let Some(list) = item_list(item) {
+            return self.list(list);
+        }
+        let item = deref_item(item);
+        unsafe {
+            Ok(match item.nType() {
+                ITEMTYPE_STRING => RingValue::String(item_bytes(item).unwrap_or_default().to_vec()),
+                ITEMTYPE_NUMBER => {
+                    RingValue::Number(ring_item_getnumber(item as *const Item as RingItem))
+                }
+                ITEMTYPE_POINTER => RingValue::Pointer(item.data.pPointer),
+                ITEMTYPE_FUNCPOINTER => match item.data.pFunc {
+                    Some(func) => RingValue::FuncPointer(func),
+                    None => RingValue::Nothing,
+                },
+                _ => RingValue::Nothing,
+            })
+        }
+    }
+
+    fn list(&mut self, list: RingList) -> RingResult<RingValue> {
+        if ring_list_iscpointerlist(list) {
+            return Ok(RingValue::CPointer {
+                ptr: ring_list_getpointer(list, 1),
+                type_name: ring_list_getstring_str(list, 2),
+            });
+        }
+        if self.path.contains(&list) {
+            return Err(RingError::new("list contains itself and can't be copied"));
+        }
+        if self.path.len() == MAX_DEPTH {
+            return Err(RingError::new(format!(
+                "list nested deeper than {MAX_DEPTH} levels can't be copied"
+            )));
+        }
+        self.path.push(list);
+        let value = self.list_contents(list);
+        self.path.pop();
+        value
+    }
+
+    fn list_contents(&mut self, list: RingList) -> RingResult<RingValue> {
+        if ring_list_isobject(list) {
+            let class_ptr = ring_list_getpointer(list, OBJECT_CLASS);
+            let class = if class_ptr.is_null() {
+                String::new()
+            } else {
+                ring_list_getstring_str(class_ptr as RingList, 1)
+            };
+            let data = ring_list_getlist(list, OBJECT_DATA);
+            let mut attributes = Vec::new();
+            for i in 1..=ring_list_getsize(data) {
+                let var = ring_list_getlist(data, i);
+                let name = ring_list_getstring_str(var, RING_VAR_NAME);
+                if name != "self" {
+                    let value = with_var_item(var, |item| self.item(item))?;
+                    attributes.push((name, value));
+                }
+            }
+            return Ok(RingValue::Object {
+                class,
+                class_ptr,
+                attributes,
+            });
+        }
+        (1..=ring_list_getsize(list))
+            .map(|i| self.item(unsafe { &*ring_list_getitem(list, i) }))
+            .collect::<RingResult<_>>()
+            .map(RingValue::List)
+    }
+}
+
+/// Turn `item` into an empty list and return it.
+fn new_item_list(item: RingItem) -> RingList {
+    ring_item_settype(item, ITEMTYPE_LIST);
+    unsafe { (*item).data.pList }
+}
+
+/// Point an object's `self` attribute at the item or variable that holds it.
+pub(crate) fn ring_object_setself(object: RingList, ptr: *mut c_void, objtype: c_uint) {
+    let data = ring_list_getlist(object, OBJECT_DATA);
+    for i in 1..=ring_list_getsize(data) {
+        let var = ring_list_getlist(data, i);
+        if ring_list_getstring_str(var, RING_VAR_NAME) == "self" {
+            ring_list_setpointer(var, RING_VAR_VALUE, ptr);
+            crate::ring_list_setint(var, crate::RING_VAR_PVALUETYPE, objtype as _);
+            return;
+        }
+    }
+}
+
+impl<'a> FromRing<'a> for RingValue {
+    fn from_item(item: &'a Item) -> RingResult<Self> {
+        RingValue::from_item(item)
+    }
+}
+
+/// Objects with a null `class_ptr` are written as lists of `[name, value]`
+/// pairs; use [`RingValue::to_item`] to reject them instead.
+impl IntoRing for RingValue {
+    fn into_item(self, item: RingItem) {
+        self.write(item);
+    }
+}
+
+impl From<f64> for RingValue {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<&str> for RingValue {
+    fn from(s: &str) -> Self {
+        Self::String(s.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for RingValue {
+    fn from(s: String) -> Self {
+        Self::String(s.into_bytes())
+    }
+}
+
+impl From<Vec<RingValue>> for RingValue {
+    fn from(values: Vec<RingValue>) -> Self {
+        Self::List(values)
+    }
+}
+
+impl From<CPointer> for RingValue {
+    fn from(p: CPointer) -> Self {
+        Self::CPointer {
+            ptr: p.ptr,
+            type_name: p.type_name,
+        }
+    }
+}
@@ -1,6 +1,9 @@
-use crate::ffi_types::{CString, c_char, c_int, c_uint, c_void};
+use crate::ffi_types::{CStr, CString, c_char, c_int, c_uint, c_void};
 
+use crate::RingResult;
+use crate::convert::{FromRing, RingArgs};
 use crate::ffi::{self, ITEM_NUMBERFLAG_DOUBLE, ITEMTYPE_STRING, RING_FALSE, RingVM};
+use crate::list::ring_list_findstring_str;
 
 #[inline]
 pub fn ring_vm_callfunction(vm: RingVM, func_name: &[u8]) {
@@ -153,6 +156,48 @@ pub unsafe fn ring_vm_stack_push_int(vm: RingVM, num: c_int) {
     }
 }
 
+/// Whether a Ring or registered C function with this (lowercase) name exists.
+pub fn ring_vm_funcexists(vm: RingVM, func_name: &str) -> bool {
+    unsafe {
+        let functions = (*vm).pFunctionsMap;
+        if !functions.is_null() && ring_list_findstring_str(functions, func_name, 1) > 0 {
+            return true;
+        }
+        let mut cfunc = (*vm).pCFunction;
+        while !cfunc.is_null() {
+            if !(*cfunc).cName.is_null()
+                && CStr::from_ptr((*cfunc).cName)
+                    .to_bytes()
+                    .eq_ignore_ascii_case(func_name.as_bytes())
+            {
+                return true;
+            }
+            cfunc = (*cfunc).pNext;
+        }
+    }
+    false
+}
+
+/// Call a Ring function (or a registered C function) with arguments and
+/// convert its return value.
+///
+/// The call runs under `try`/`catch`, like [`Ring::call`](crate::Ring::call):
+/// a runtime error in the function, a missing function or more arguments than
+/// the VM stack holds are returned as errors. List arguments are passed as
+/// copies owned by the VM, so the function may keep them.
+///
+/// ```rust,ignore
+/// let sum: f64 = ring_vm_call(vm, "add", (1, 2))?;
+/// let text: String = ring_vm_call(vm, "describe", ("name", vec![1.5, 2.5]))?;
+/// ```
+pub fn ring_vm_call<R, A>(vm: RingVM, func_name: &str, args: A) -> RingResult<R>
+where
+    R: for<'a> FromRing<'a>,
+    A: RingArgs,
+{
+    crate::interpreter::call_catching(vm, func_name, args)
+}
+
 // VM execution and utilities
 
 #[inline]